@@ -227,7 +227,6 @@ export class NodeTronAdapter extends BaseTronAdapter {
           meta: {
             executionTimeMs: executionTime,
             commandsExecuted: 1, // Would need deeper integration to track
-            sideEffects: [], // Would need side-effect tracking
           },
         },
       };
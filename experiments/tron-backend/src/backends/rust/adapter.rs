@@ -19,11 +19,18 @@
 //!     .route("/compile", post(backend.compile_handler()));
 //! ```
 
-use std::sync::Arc;
+use std::any::Any;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
 use std::time::{Duration, Instant};
 
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncReadExt};
 
 // =============================================================================
 // FFI Bindings to Lite³
@@ -55,6 +62,24 @@ mod ffi {
             value: i64,
         );
         pub fn lite3_ctx_get_buffer(ctx: *mut Lite3Ctx, len: *mut usize) -> *const c_char;
+
+        /// Copies `buf` into a new context; null if it is not a valid document
+        pub fn lite3_ctx_from_buffer(buf: *const c_void, len: usize) -> *mut Lite3Ctx;
+        /// Returns 0 and writes `value` when `key` holds an integer
+        pub fn lite3_ctx_get_i64(
+            ctx: *mut Lite3Ctx,
+            parent: c_int,
+            key: *const c_char,
+            value: *mut i64,
+        ) -> c_int;
+        /// Borrowed NUL-terminated string, valid until the context is destroyed
+        pub fn lite3_ctx_get_str(
+            ctx: *mut Lite3Ctx,
+            parent: c_int,
+            key: *const c_char,
+        ) -> *const c_char;
+        /// Index of a nested object for use as `parent`, or -1
+        pub fn lite3_ctx_get_obj(ctx: *mut Lite3Ctx, parent: c_int, key: *const c_char) -> c_int;
     }
 }
 
@@ -81,6 +106,17 @@ pub struct Config {
     pub max_message_size: usize,
     pub timeout: Duration,
     pub debug: bool,
+    /// How long results are kept for `ExecuteRequest.idempotency_key` replays
+    pub idempotency_window: Duration,
+    /// Most idempotency keys remembered at once; the oldest completed entry is
+    /// evicted to make room
+    pub max_idempotency_entries: usize,
+    /// Largest hyperscript source accepted by `compile` and `execute`
+    pub max_source_bytes: usize,
+    /// Consecutive native library failures before its circuit opens
+    pub breaker_threshold: u32,
+    /// How long an open circuit short-circuits before a trial call
+    pub breaker_cooldown: Duration,
 }
 
 impl Default for Config {
@@ -94,6 +130,11 @@ impl Default for Config {
             max_message_size: 10 * 1024 * 1024, // 10MB
             timeout: Duration::from_secs(30),
             debug: false,
+            idempotency_window: Duration::from_secs(5 * 60),
+            max_idempotency_entries: 10_000,
+            max_source_bytes: 1024 * 1024, // 1MB
+            breaker_threshold: 5,
+            breaker_cooldown: Duration::from_secs(30),
         }
     }
 }
@@ -105,6 +146,37 @@ impl Default for Config {
 /// TRON magic number: "TRON" in ASCII
 pub const TRON_MAGIC: u32 = 0x54524F4E;
 
+/// Current hyperscript grammar version
+pub const GRAMMAR_VERSION: u32 = 1;
+
+/// Oldest grammar version still selectable via `CompileOptions.grammar_version`
+pub const MIN_GRAMMAR_VERSION: u32 = 1;
+
+/// Current `CompileResult`/`ExecuteResult` schema version.
+///
+/// Results decode tolerantly: unknown fields from newer writers are ignored and
+/// payloads written before the field existed decode as version 1.
+pub const RESULT_SCHEMA_VERSION: u16 = 1;
+
+fn legacy_schema_version() -> u16 {
+    1
+}
+
+/// Current `Extensions` schema version
+pub const EXTENSIONS_VERSION: u16 = 1;
+
+/// Header feature flags (mirrors `TronFlags` in protocol.ts)
+pub mod flags {
+    pub const COMPRESSED: u16 = 1 << 0;
+    pub const STREAMING: u16 = 1 << 1;
+    pub const ENCRYPTED: u16 = 1 << 2;
+    pub const CHECKSUM: u16 = 1 << 3;
+    /// Message is followed by a signature trailer
+    pub const SIGNED: u16 = 1 << 4;
+    /// Message is followed by an attachment section
+    pub const ATTACHMENTS: u16 = 1 << 5;
+}
+
 /// TRON header
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TronHeader {
@@ -130,6 +202,13 @@ pub struct TronMessage<T> {
     pub payload: T,
 }
 
+/// Body of an emulated message: the full `{header, payload}` JSON, as the
+/// Node adapter writes it. Only `payload` is read back.
+#[derive(Deserialize)]
+struct EmulatedEnvelope<T> {
+    payload: T,
+}
+
 /// Compile request
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompileRequest {
@@ -153,17 +232,195 @@ pub struct CompileOptions {
     pub source_map: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub target: Option<String>,
+    /// Locale for error messages (defaults to the request language)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    /// Handling of legacy _hyperscript constructs. Not enforced yet: compiling
+    /// with a dialect set adds a warning to `CompileMeta.warnings`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dialect: Option<Dialect>,
+    /// Grammar version to parse with (defaults to `GRAMMAR_VERSION`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grammar_version: Option<u32>,
+    /// Options for third-party plugins
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extensions: Option<Extensions>,
+}
+
+/// Plugin options carried through the protocol untouched.
+///
+/// Keys are plugin-defined (namespacing such as `"my-plugin.option"` is
+/// recommended); the backend never interprets the values.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Extensions {
+    #[serde(default = "default_extensions_version")]
+    pub version: u16,
+    #[serde(default)]
+    pub values: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Default for Extensions {
+    fn default() -> Self {
+        Self {
+            version: EXTENSIONS_VERSION,
+            values: serde_json::Map::new(),
+        }
+    }
+}
+
+fn default_extensions_version() -> u16 {
+    EXTENSIONS_VERSION
+}
+
+/// Strictness level for legacy/quirky _hyperscript constructs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Dialect {
+    /// Reject legacy constructs
+    Strict,
+    /// Accept legacy constructs with a warning
+    #[default]
+    Compatible,
+    /// Accept legacy constructs silently
+    Permissive,
+}
+
+impl CompileRequest {
+    /// Locale used for diagnostics: explicit `options.locale`, else `language`
+    pub fn locale(&self) -> Option<&str> {
+        self.options
+            .as_ref()
+            .and_then(|o| o.locale.as_deref())
+            .or(self.language.as_deref())
+    }
 }
 
 /// Compile result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompileResult {
+    #[serde(default = "legacy_schema_version")]
+    pub schema_version: u16,
     pub ast: Vec<u8>,
     pub meta: CompileMeta,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub source_map: Option<String>,
 }
 
+impl CompileResult {
+    /// Stable fingerprint of the whole result except `meta.compile_time_ms`.
+    ///
+    /// FNV-1a over the JSON serialization, so it is identical across processes
+    /// and builds, and changes with warnings, grammar version or provenance.
+    pub fn fingerprint(&self) -> String {
+        let untimed = CompileResult {
+            meta: CompileMeta {
+                compile_time_ms: None,
+                ..self.meta.clone()
+            },
+            ..self.clone()
+        };
+        let canonical = serde_json::to_vec(&untimed).expect("CompileResult serializes to JSON");
+        fnv1a(&[&canonical])
+    }
+}
+
+/// 64-bit FNV-1a over the concatenated parts, as 16 hex digits
+fn fnv1a(parts: &[&[u8]]) -> String {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let hash = parts
+        .iter()
+        .flat_map(|part| part.iter())
+        .fold(OFFSET, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(PRIME)
+        });
+    format!("{:016x}", hash)
+}
+
+/// Build information embedded in every compiled artifact
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// Version of this crate that produced the artifact
+    pub compiler_version: String,
+    pub grammar_version: u32,
+    /// Cargo features enabled in the producing build
+    pub features: Vec<String>,
+    /// Fingerprint of the `CompileOptions` used
+    pub options_hash: String,
+}
+
+impl Provenance {
+    /// Provenance for an artifact compiled by this build
+    pub fn current(grammar_version: u32, options: Option<&CompileOptions>) -> Self {
+        let options = serde_json::to_vec(&options).unwrap_or_default();
+        let features = [
+            ("native", cfg!(feature = "native")),
+            ("axum", cfg!(feature = "axum")),
+            ("signing", cfg!(feature = "signing")),
+            ("admin", cfg!(feature = "admin")),
+        ];
+
+        Self {
+            compiler_version: env!("CARGO_PKG_VERSION").to_string(),
+            grammar_version,
+            features: features
+                .iter()
+                .filter(|(_, enabled)| *enabled)
+                .map(|(name, _)| name.to_string())
+                .collect(),
+            options_hash: fnv1a(&[&options]),
+        }
+    }
+}
+
+/// Check that an artifact was produced by a compatible compiler.
+///
+/// Requires embedded provenance, a compiler version that is semver-compatible
+/// with this build, and a grammar version this build still supports.
+///
+/// Callers holding a `CompileResult` must check it themselves: `execute` only
+/// receives the AST bytes, which carry no provenance yet.
+pub fn verify_provenance(artifact: &CompileResult) -> TronResult<()> {
+    let incompatible = |message: String| TronError {
+        code: ErrorCode::UnsupportedVersion,
+        message,
+        context: None,
+    };
+
+    let provenance = artifact
+        .meta
+        .provenance
+        .as_ref()
+        .ok_or_else(|| incompatible("Artifact has no provenance".to_string()))?;
+
+    // Same major version, or same minor while still on 0.x
+    let compat_prefix = |version: &str| {
+        let mut parts = version.split('.');
+        match (parts.next(), parts.next()) {
+            (Some("0"), Some(minor)) => format!("0.{}", minor),
+            (Some(major), _) => major.to_string(),
+            _ => String::new(),
+        }
+    };
+    let current = env!("CARGO_PKG_VERSION");
+    if compat_prefix(&provenance.compiler_version) != compat_prefix(current) {
+        return Err(incompatible(format!(
+            "Artifact compiled by {}, incompatible with {}",
+            provenance.compiler_version, current
+        )));
+    }
+
+    if !(MIN_GRAMMAR_VERSION..=GRAMMAR_VERSION).contains(&provenance.grammar_version) {
+        return Err(incompatible(format!(
+            "Artifact uses grammar version {} (supported: {}-{})",
+            provenance.grammar_version, MIN_GRAMMAR_VERSION, GRAMMAR_VERSION
+        )));
+    }
+
+    Ok(())
+}
+
 /// Compile metadata
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CompileMeta {
@@ -177,6 +434,10 @@ pub struct CompileMeta {
     pub warnings: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub compile_time_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grammar_version: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
 }
 
 /// Execute request
@@ -187,6 +448,18 @@ pub struct ExecuteRequest {
     pub context: Option<ExecutionContext>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub target: Option<String>,
+    /// Retries with the same key within `Config.idempotency_window` return the
+    /// stored result instead of executing again. Reusing a key for a different
+    /// request is an `InvalidMessage` error.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
+    /// Record side effects (fetch, DOM writes, sends) into a plan instead of
+    /// performing them. Dry runs ignore `idempotency_key`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dry_run: Option<bool>,
+    /// Options for third-party plugins
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extensions: Option<Extensions>,
 }
 
 /// Code source - either source string or pre-compiled AST
@@ -198,25 +471,114 @@ pub enum CodeSource {
 }
 
 /// Execution context
+///
+/// Locals and globals are shared behind `Arc`, so cloning or forking a context
+/// is cheap; the first write through `locals_mut`/`globals_mut` copies the value.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ExecutionContext {
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub locals: Option<serde_json::Value>,
+    pub locals: Option<Arc<serde_json::Value>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub globals: Option<serde_json::Value>,
+    pub globals: Option<Arc<serde_json::Value>>,
+}
+
+impl ExecutionContext {
+    /// Fork the context without copying locals or globals
+    pub fn fork(&self) -> Self {
+        self.clone()
+    }
+
+    /// Mutable locals, copied first if shared with another context
+    pub fn locals_mut(&mut self) -> &mut serde_json::Value {
+        Arc::make_mut(self.locals.get_or_insert_with(empty_object))
+    }
+
+    /// Mutable globals, copied first if shared with another context
+    pub fn globals_mut(&mut self) -> &mut serde_json::Value {
+        Arc::make_mut(self.globals.get_or_insert_with(empty_object))
+    }
+}
+
+fn empty_object() -> Arc<serde_json::Value> {
+    Arc::new(serde_json::Value::Object(serde_json::Map::new()))
 }
 
 /// Execute result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExecuteResult {
+    #[serde(default = "legacy_schema_version")]
+    pub schema_version: u16,
     pub success: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub value: Option<serde_json::Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub context: Option<ExecutionContext>,
+    /// Effects that would have been performed (dry runs only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plan: Option<EffectPlan>,
     pub meta: ExecuteMeta,
 }
 
+/// Ordered side effects recorded during a dry run. Supersedes the deprecated
+/// `ExecuteMeta.sideEffects` in `protocol.ts`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EffectPlan {
+    pub effects: Vec<Effect>,
+}
+
+/// A single side effect with its arguments
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Effect {
+    /// Outbound HTTP request (`fetch`)
+    Fetch {
+        url: String,
+        method: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        body: Option<serde_json::Value>,
+    },
+    /// DOM mutation (`put`, `add`, `remove`, `toggle`, `set`)
+    DomWrite {
+        command: String,
+        target: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        value: Option<serde_json::Value>,
+    },
+    /// Event dispatch (`send`, `trigger`)
+    Send {
+        event: String,
+        target: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        detail: Option<serde_json::Value>,
+    },
+    /// Any other side-effecting command
+    Command {
+        command: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        args: Option<serde_json::Value>,
+    },
+}
+
+/// Receiver for effects taken from an `EffectPlan`
+pub trait EffectTarget {
+    fn apply_effect(&mut self, effect: &Effect) -> TronResult<()>;
+}
+
+impl EffectPlan {
+    /// Drop effects rejected by a policy, preserving order
+    pub fn filter(mut self, mut allow: impl FnMut(&Effect) -> bool) -> Self {
+        self.effects.retain(|effect| allow(effect));
+        self
+    }
+}
+
+/// Apply a plan in order, stopping at the first failed effect
+pub fn apply(plan: &EffectPlan, target: &mut impl EffectTarget) -> TronResult<()> {
+    plan.effects
+        .iter()
+        .try_for_each(|effect| target.apply_effect(effect))
+}
+
 /// Execution metadata
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ExecuteMeta {
@@ -224,6 +586,9 @@ pub struct ExecuteMeta {
     pub execution_time_ms: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub commands_executed: Option<u32>,
+    /// Set when the result was replayed for a repeated idempotency key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replayed: Option<bool>,
 }
 
 // =============================================================================
@@ -238,6 +603,7 @@ pub enum ErrorCode {
     UnsupportedVersion = 1001,
     InvalidPayloadType = 1002,
     ChecksumMismatch = 1003,
+    SignatureInvalid = 1004,
     ParseError = 2000,
     SyntaxError = 2001,
     UnsupportedLanguage = 2002,
@@ -257,318 +623,2472 @@ pub struct TronError {
     pub context: Option<serde_json::Value>,
 }
 
-/// Result type alias
-pub type TronResult<T> = Result<T, TronError>;
-
-// =============================================================================
-// Backend Implementation
-// =============================================================================
-
-/// TRON backend adapter
-pub struct TronBackend {
-    config: Config,
-    ready: std::sync::atomic::AtomicBool,
+impl ErrorCode {
+    /// Short description of the error code in the given locale.
+    ///
+    /// Accepts ISO 639-1 codes with optional region (`es`, `es-MX`) and falls
+    /// back to English for unknown locales.
+    pub fn summary(&self, locale: Option<&str>) -> &'static str {
+        let lang = locale
+            .and_then(|l| l.split(['-', '_']).next())
+            .unwrap_or("en");
+        messages::lookup(*self, lang).unwrap_or_else(|| messages::english(*self))
+    }
 }
 
-impl TronBackend {
-    /// Create a new TRON backend
-    pub fn new(config: Config) -> Self {
-        Self {
-            config,
-            ready: std::sync::atomic::AtomicBool::new(false),
+impl TronError {
+    /// Prefix the message with the localized summary for its code.
+    ///
+    /// English (and unknown) locales leave the message untouched.
+    pub fn localize(mut self, locale: Option<&str>) -> Self {
+        if let Some(lang) = locale.and_then(|l| l.split(['-', '_']).next()) {
+            if let Some(summary) = messages::lookup(self.code, lang) {
+                if lang != "en" {
+                    self.message = format!("{}: {}", summary, self.message);
+                }
+            }
         }
+        self
     }
+}
 
-    /// Initialize the backend
-    pub async fn initialize(&self) -> TronResult<()> {
-        // Initialize native TRON library if available
-        #[cfg(feature = "native")]
-        {
-            // Pre-warm the allocator
-            unsafe {
-                let ctx = ffi::lite3_ctx_create();
-                ffi::lite3_ctx_destroy(ctx);
-            }
+/// Error message catalog keyed by error code
+mod messages {
+    use super::ErrorCode;
+
+    pub fn english(code: ErrorCode) -> &'static str {
+        match code {
+            ErrorCode::InvalidMessage => "Invalid message",
+            ErrorCode::UnsupportedVersion => "Unsupported protocol version",
+            ErrorCode::InvalidPayloadType => "Invalid payload type",
+            ErrorCode::ChecksumMismatch => "Checksum mismatch",
+            ErrorCode::SignatureInvalid => "Invalid message signature",
+            ErrorCode::ParseError => "Parse error",
+            ErrorCode::SyntaxError => "Syntax error",
+            ErrorCode::UnsupportedLanguage => "Unsupported language",
+            ErrorCode::RuntimeError => "Runtime error",
+            ErrorCode::Timeout => "Execution timed out",
+            ErrorCode::InternalError => "Internal error",
+            ErrorCode::ServiceUnavailable => "Service unavailable",
         }
-
-        self.ready
-            .store(true, std::sync::atomic::Ordering::SeqCst);
-        Ok(())
     }
 
-    /// Check if backend is ready
-    pub fn is_ready(&self) -> bool {
-        self.ready.load(std::sync::atomic::Ordering::SeqCst)
+    fn spanish(code: ErrorCode) -> &'static str {
+        match code {
+            ErrorCode::InvalidMessage => "Mensaje no válido",
+            ErrorCode::UnsupportedVersion => "Versión de protocolo no compatible",
+            ErrorCode::InvalidPayloadType => "Tipo de contenido no válido",
+            ErrorCode::ChecksumMismatch => "La suma de comprobación no coincide",
+            ErrorCode::SignatureInvalid => "Firma del mensaje no válida",
+            ErrorCode::ParseError => "Error de análisis",
+            ErrorCode::SyntaxError => "Error de sintaxis",
+            ErrorCode::UnsupportedLanguage => "Idioma no compatible",
+            ErrorCode::RuntimeError => "Error de ejecución",
+            ErrorCode::Timeout => "Se agotó el tiempo de ejecución",
+            ErrorCode::InternalError => "Error interno",
+            ErrorCode::ServiceUnavailable => "Servicio no disponible",
+        }
     }
 
-    /// Encode a message to TRON format
-    pub fn encode<T: Serialize>(&self, message: &TronMessage<T>) -> TronResult<Vec<u8>> {
-        #[cfg(feature = "native")]
-        {
-            self.encode_native(message)
+    fn japanese(code: ErrorCode) -> &'static str {
+        match code {
+            ErrorCode::InvalidMessage => "無効なメッセージ",
+            ErrorCode::UnsupportedVersion => "サポートされていないプロトコルバージョン",
+            ErrorCode::InvalidPayloadType => "無効なペイロード種別",
+            ErrorCode::ChecksumMismatch => "チェックサムが一致しません",
+            ErrorCode::SignatureInvalid => "メッセージの署名が無効です",
+            ErrorCode::ParseError => "解析エラー",
+            ErrorCode::SyntaxError => "構文エラー",
+            ErrorCode::UnsupportedLanguage => "サポートされていない言語",
+            ErrorCode::RuntimeError => "実行時エラー",
+            ErrorCode::Timeout => "実行がタイムアウトしました",
+            ErrorCode::InternalError => "内部エラー",
+            ErrorCode::ServiceUnavailable => "サービスを利用できません",
         }
+    }
 
-        #[cfg(not(feature = "native"))]
-        {
-            self.encode_emulated(message)
+    pub fn lookup(code: ErrorCode, lang: &str) -> Option<&'static str> {
+        match lang {
+            "en" => Some(english(code)),
+            "es" => Some(spanish(code)),
+            "ja" => Some(japanese(code)),
+            _ => None,
         }
     }
+}
 
-    /// Decode a TRON message
-    pub fn decode<T: for<'de> Deserialize<'de>>(&self, data: &[u8]) -> TronResult<TronMessage<T>> {
-        #[cfg(feature = "native")]
-        {
-            self.decode_native(data)
-        }
+/// Result type alias
+pub type TronResult<T> = Result<T, TronError>;
 
-        #[cfg(not(feature = "native"))]
-        {
-            self.decode_emulated(data)
-        }
-    }
+// =============================================================================
+// Message Signing
+// =============================================================================
 
-    /// Compile hyperscript source
-    pub async fn compile(&self, request: CompileRequest) -> TronResult<CompileResult> {
-        let start = Instant::now();
+/// Produces signatures for outgoing messages
+pub trait Signer: Send + Sync {
+    fn sign(&self, data: &[u8]) -> Vec<u8>;
+}
 
-        // TODO: Integrate with HyperFixi WASM module
-        // For now, return a placeholder
+/// Checks signatures on incoming messages
+pub trait Verifier: Send + Sync {
+    fn verify(&self, data: &[u8], signature: &[u8]) -> bool;
+}
 
-        Ok(CompileResult {
-            ast: Vec::new(),
-            meta: CompileMeta {
-                parser_used: Some("placeholder".to_string()),
-                compile_time_ms: Some(start.elapsed().as_secs_f64() * 1000.0),
-                ..Default::default()
-            },
-            source_map: None,
-        })
+#[cfg(feature = "signing")]
+impl Signer for ed25519_dalek::SigningKey {
+    fn sign(&self, data: &[u8]) -> Vec<u8> {
+        ed25519_dalek::Signer::sign(self, data).to_bytes().to_vec()
     }
+}
 
-    /// Execute hyperscript
-    pub async fn execute(&self, request: ExecuteRequest) -> TronResult<ExecuteResult> {
-        let start = Instant::now();
+#[cfg(feature = "signing")]
+impl Verifier for ed25519_dalek::VerifyingKey {
+    fn verify(&self, data: &[u8], signature: &[u8]) -> bool {
+        ed25519_dalek::Signature::from_slice(signature)
+            .map(|signature| self.verify_strict(data, &signature).is_ok())
+            .unwrap_or(false)
+    }
+}
 
-        // TODO: Integrate with HyperFixi WASM module
+// =============================================================================
+// Host Value Interop
+// =============================================================================
 
-        Ok(ExecuteResult {
-            success: true,
-            value: None,
-            context: request.context,
-            meta: ExecuteMeta {
-                execution_time_ms: Some(start.elapsed().as_secs_f64() * 1000.0),
-                commands_executed: Some(1),
-            },
+/// Opaque reference to a host-owned resource.
+///
+/// Scripts see `{"$handle": id}` and can pass it back to host code, but the
+/// resource itself never crosses into the execution context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Handle {
+    #[serde(rename = "$handle")]
+    pub id: u64,
+}
+
+/// Conversion from a host type into a script value
+pub trait ToLoka {
+    fn to_loka(&self) -> TronResult<serde_json::Value>;
+}
+
+/// Conversion from a script value back into a host type
+pub trait FromLoka: Sized {
+    fn from_loka(value: &serde_json::Value) -> TronResult<Self>;
+}
+
+impl<T: Serialize> ToLoka for T {
+    fn to_loka(&self) -> TronResult<serde_json::Value> {
+        serde_json::to_value(self).map_err(|e| TronError {
+            code: ErrorCode::InvalidPayloadType,
+            message: format!("Failed to convert host value: {}", e),
+            context: None,
         })
     }
+}
 
-    // -------------------------------------------------------------------------
-    // Private Methods
-    // -------------------------------------------------------------------------
+impl<T: for<'de> Deserialize<'de>> FromLoka for T {
+    fn from_loka(value: &serde_json::Value) -> TronResult<Self> {
+        T::deserialize(value).map_err(|e| TronError {
+            code: ErrorCode::InvalidPayloadType,
+            message: format!("Failed to convert script value: {}", e),
+            context: None,
+        })
+    }
+}
 
-    #[cfg(feature = "native")]
-    fn encode_native<T: Serialize>(&self, message: &TronMessage<T>) -> TronResult<Vec<u8>> {
-        use std::ffi::CString;
+impl ExecutionContext {
+    /// Set a local variable from a host value
+    pub fn set_local(&mut self, name: &str, value: &impl ToLoka) -> TronResult<()> {
+        insert_field(self.locals_mut(), name, value.to_loka()?)
+    }
 
-        unsafe {
-            let ctx = ffi::lite3_ctx_create();
-            ffi::lite3_ctx_init_obj(ctx);
+    /// Set a global variable from a host value
+    pub fn set_global(&mut self, name: &str, value: &impl ToLoka) -> TronResult<()> {
+        insert_field(self.globals_mut(), name, value.to_loka()?)
+    }
 
-            // Set header
-            let magic_key = CString::new("magic").unwrap();
-            ffi::lite3_ctx_set_i64(ctx, 0, magic_key.as_ptr(), message.header.magic as i64);
+    /// Read a local variable as a host type
+    pub fn local<T: FromLoka>(&self, name: &str) -> TronResult<Option<T>> {
+        self.locals
+            .as_ref()
+            .and_then(|locals| locals.get(name))
+            .map(T::from_loka)
+            .transpose()
+    }
 
-            let version_key = CString::new("version").unwrap();
-            ffi::lite3_ctx_set_i64(ctx, 0, version_key.as_ptr(), message.header.version as i64);
+    /// Read a global variable as a host type
+    pub fn global<T: FromLoka>(&self, name: &str) -> TronResult<Option<T>> {
+        self.globals
+            .as_ref()
+            .and_then(|globals| globals.get(name))
+            .map(T::from_loka)
+            .transpose()
+    }
+}
 
-            let flags_key = CString::new("flags").unwrap();
-            ffi::lite3_ctx_set_i64(ctx, 0, flags_key.as_ptr(), message.header.flags as i64);
+impl Extensions {
+    /// Read a plugin option as a host type
+    pub fn get<T: FromLoka>(&self, key: &str) -> TronResult<Option<T>> {
+        self.values.get(key).map(T::from_loka).transpose()
+    }
 
-            // Serialize payload
-            let payload_json = serde_json::to_string(&message.payload).map_err(|e| TronError {
-                code: ErrorCode::InternalError,
-                message: format!("Failed to serialize payload: {}", e),
-                context: None,
-            })?;
+    /// Set a plugin option from a host value
+    pub fn insert(&mut self, key: impl Into<String>, value: &impl ToLoka) -> TronResult<()> {
+        self.values.insert(key.into(), value.to_loka()?);
+        Ok(())
+    }
+}
 
-            let payload_key = CString::new("payload").unwrap();
-            let payload_value = CString::new(payload_json).unwrap();
-            ffi::lite3_ctx_set_str(ctx, 0, payload_key.as_ptr(), payload_value.as_ptr());
+impl ExecuteResult {
+    /// Read the result value as a host type
+    pub fn value_as<T: FromLoka>(&self) -> TronResult<Option<T>> {
+        self.value.as_ref().map(T::from_loka).transpose()
+    }
+}
 
-            // Get buffer
-            let mut len: usize = 0;
-            let buf_ptr = ffi::lite3_ctx_get_buffer(ctx, &mut len);
-            let result = std::slice::from_raw_parts(buf_ptr as *const u8, len).to_vec();
+/// Host-owned resources (connections, file handles) referenced by scripts
+/// through opaque `Handle`s.
+///
+/// Dropping the table drops every resource still registered; `close` does the
+/// same but reports which ones the script never released.
+#[derive(Default)]
+pub struct ResourceTable {
+    next_id: u64,
+    entries: HashMap<u64, (String, Box<dyn Any + Send + Sync>)>,
+}
 
-            ffi::lite3_ctx_destroy(ctx);
+/// A resource still registered when its table was closed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeakedResource {
+    pub handle: Handle,
+    pub label: String,
+}
 
-            Ok(result)
-        }
+impl ResourceTable {
+    pub fn new() -> Self {
+        Self::default()
     }
 
-    #[cfg(not(feature = "native"))]
-    fn encode_emulated<T: Serialize>(&self, message: &TronMessage<T>) -> TronResult<Vec<u8>> {
-        // JSON-based emulation for development
-        let json = serde_json::to_vec(message).map_err(|e| TronError {
-            code: ErrorCode::InternalError,
-            message: format!("Failed to encode: {}", e),
-            context: None,
-        })?;
+    /// Register a resource and return the handle scripts will see
+    pub fn insert<T: Any + Send + Sync>(
+        &mut self,
+        label: impl Into<String>,
+        resource: T,
+    ) -> Handle {
+        self.next_id += 1;
+        self.entries
+            .insert(self.next_id, (label.into(), Box::new(resource)));
+        Handle { id: self.next_id }
+    }
 
-        // Prepend header (8 bytes)
-        let mut result = Vec::with_capacity(8 + json.len());
+    /// Borrow a resource, if the handle is live and of type `T`
+    pub fn get<T: Any>(&self, handle: Handle) -> Option<&T> {
+        self.entries
+            .get(&handle.id)
+            .and_then(|(_, resource)| resource.downcast_ref())
+    }
 
-        // Magic (4 bytes, big-endian)
-        result.extend_from_slice(&message.header.magic.to_be_bytes());
-        // Version (2 bytes, big-endian)
-        result.extend_from_slice(&message.header.version.to_be_bytes());
-        // Flags (2 bytes, big-endian)
-        result.extend_from_slice(&message.header.flags.to_be_bytes());
-        // Payload
-        result.extend_from_slice(&json);
+    /// Mutably borrow a resource, if the handle is live and of type `T`
+    pub fn get_mut<T: Any>(&mut self, handle: Handle) -> Option<&mut T> {
+        self.entries
+            .get_mut(&handle.id)
+            .and_then(|(_, resource)| resource.downcast_mut())
+    }
 
-        Ok(result)
+    /// Release a resource, returning ownership to the host
+    pub fn remove<T: Any>(&mut self, handle: Handle) -> Option<T> {
+        // Check the type first so a mismatched `T` leaves the entry registered
+        self.get::<T>(handle)?;
+        self.entries
+            .remove(&handle.id)
+            .and_then(|(_, resource)| resource.downcast().ok())
+            .map(|resource| *resource)
     }
 
-    #[cfg(feature = "native")]
-    fn decode_native<T: for<'de> Deserialize<'de>>(
-        &self,
-        data: &[u8],
-    ) -> TronResult<TronMessage<T>> {
-        // For native, we'd use lite3_ctx_from_buffer
-        // Fallback to JSON for now
-        self.decode_emulated(data)
+    pub fn len(&self) -> usize {
+        self.entries.len()
     }
 
-    #[cfg(not(feature = "native"))]
-    fn decode_emulated<T: for<'de> Deserialize<'de>>(
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Drop all remaining resources and report them as leaked
+    pub fn close(mut self) -> Vec<LeakedResource> {
+        let mut leaked: Vec<_> = self
+            .entries
+            .drain()
+            .map(|(id, (label, _))| LeakedResource {
+                handle: Handle { id },
+                label,
+            })
+            .collect();
+        leaked.sort_by_key(|leak| leak.handle.id);
+        leaked
+    }
+}
+
+impl std::fmt::Debug for ResourceTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResourceTable")
+            .field("live", &self.entries.len())
+            .finish()
+    }
+}
+
+fn insert_field(
+    scope: &mut serde_json::Value,
+    name: &str,
+    value: serde_json::Value,
+) -> TronResult<()> {
+    match scope {
+        serde_json::Value::Object(map) => {
+            map.insert(name.to_string(), value);
+            Ok(())
+        }
+        _ => Err(TronError {
+            code: ErrorCode::InvalidPayloadType,
+            message: format!("Cannot set `{}`: scope is not an object", name),
+            context: None,
+        }),
+    }
+}
+
+// =============================================================================
+// Backend Implementation
+// =============================================================================
+
+/// TRON backend adapter
+pub struct TronBackend {
+    config: Config,
+    ready: std::sync::atomic::AtomicBool,
+    idempotent_results: Mutex<HashMap<String, IdempotentEntry>>,
+    recent_errors: Mutex<VecDeque<RecentError>>,
+    crash_reports: Mutex<VecDeque<CrashReport>>,
+    crash_sequence: std::sync::atomic::AtomicU64,
+    native_breaker: CircuitBreaker,
+    observer: Option<Arc<dyn StageObserver>>,
+}
+
+/// Pipeline stage reported to a `StageObserver`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    Decode,
+    Compile,
+    Execute,
+    Encode,
+}
+
+/// Receives per-stage timings, for embedders feeding their own telemetry.
+///
+/// `TronBackend::self_test` is reported too; run it on a backend built without
+/// `with_observer` to keep it out of production metrics.
+pub trait StageObserver: Send + Sync {
+    /// Called once per stage after it finishes, whether or not it succeeded
+    fn on_stage(&self, stage: Stage, elapsed: Duration, success: bool);
+}
+
+/// Execution remembered under an `ExecuteRequest.idempotency_key`
+struct IdempotentEntry {
+    /// Hash of the request, so a reused key can't replay a different script
+    fingerprint: String,
+    stored_at: Instant,
+    state: IdempotentState,
+}
+
+enum IdempotentState {
+    /// Executing; retries wait on the `Notify` instead of running again
+    InFlight(Arc<tokio::sync::Notify>),
+    Done(ExecuteResult),
+}
+
+/// Outcome of claiming an idempotency key
+enum Claim<'a> {
+    Replay(ExecuteResult),
+    Execute(InFlightGuard<'a>),
+}
+
+/// Releases an in-flight idempotency key if execution ends without storing a
+/// result (error, panic or a dropped future), waking any waiting retries
+struct InFlightGuard<'a> {
+    results: &'a Mutex<HashMap<String, IdempotentEntry>>,
+    key: String,
+    notify: Arc<tokio::sync::Notify>,
+}
+
+impl InFlightGuard<'_> {
+    fn complete(self, result: &ExecuteResult) {
+        if let Some(entry) = self.results.lock().unwrap().get_mut(&self.key) {
+            entry.stored_at = Instant::now();
+            entry.state = IdempotentState::Done(result.clone());
+        }
+        // `Drop` wakes waiters; the entry is no longer in flight so it stays
+    }
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        let mut results = self.results.lock().unwrap();
+        if let Some(IdempotentEntry {
+            state: IdempotentState::InFlight(_),
+            ..
+        }) = results.get(&self.key)
+        {
+            results.remove(&self.key);
+        }
+        drop(results);
+        self.notify.notify_waiters();
+    }
+}
+
+/// Number of errors kept for `TronBackend::stats`
+const RECENT_ERROR_LIMIT: usize = 20;
+
+/// Snapshot of backend state for monitoring
+#[derive(Debug, Clone, Serialize)]
+pub struct BackendStats {
+    pub ready: bool,
+    pub protocol_version: u16,
+    pub idempotency_entries: usize,
+    /// State of the breaker guarding the native Lite³ library
+    pub native_breaker: BreakerState,
+    /// Most recent first
+    pub recent_errors: Vec<RecentError>,
+}
+
+/// Circuit breaker state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    /// Calls go through
+    Closed,
+    /// Calls are short-circuited until the cooldown passes
+    Open,
+    /// Cooldown passed; the next call is a trial
+    HalfOpen,
+}
+
+/// Consecutive-failure circuit breaker guarding one destination
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    inner: Mutex<BreakerInner>,
+}
+
+#[derive(Debug, Default)]
+struct BreakerInner {
+    failures: u32,
+    opened_at: Option<Instant>,
+    /// A half-open trial call has been let through and not yet recorded
+    trial_in_progress: bool,
+}
+
+impl CircuitBreaker {
+    /// Open after `threshold` consecutive failures, retry after `cooldown`
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            cooldown,
+            inner: Mutex::new(BreakerInner::default()),
+        }
+    }
+
+    pub fn state(&self) -> BreakerState {
+        match self.inner.lock().unwrap().opened_at {
+            None => BreakerState::Closed,
+            Some(at) if at.elapsed() < self.cooldown => BreakerState::Open,
+            Some(_) => BreakerState::HalfOpen,
+        }
+    }
+
+    /// Whether a call should be attempted. Once half-open, only one trial
+    /// call is let through until its outcome is recorded.
+    pub fn allow(&self) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.opened_at {
+            None => true,
+            Some(at) if at.elapsed() < self.cooldown => false,
+            Some(_) if inner.trial_in_progress => false,
+            Some(_) => {
+                inner.trial_in_progress = true;
+                true
+            }
+        }
+    }
+
+    pub fn record_success(&self) {
+        *self.inner.lock().unwrap() = BreakerInner::default();
+    }
+
+    /// Count a failure; reaching the threshold (or failing a half-open trial)
+    /// opens the circuit
+    pub fn record_failure(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.failures += 1;
+        inner.trial_in_progress = false;
+        if inner.failures >= self.threshold {
+            inner.opened_at = Some(Instant::now());
+        }
+    }
+}
+
+/// Result of `TronBackend::self_test`
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestReport {
+    pub checks: Vec<SelfTestCheck>,
+}
+
+impl SelfTestReport {
+    /// True when no check failed (skipped checks don't count)
+    pub fn passed(&self) -> bool {
+        self.checks
+            .iter()
+            .all(|check| check.status != CheckStatus::Fail)
+    }
+}
+
+/// One row of the self-test matrix
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestCheck {
+    pub name: String,
+    pub status: CheckStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Pass,
+    Fail,
+    Skipped,
+}
+
+fn self_test_failure(message: &str) -> TronError {
+    TronError {
+        code: ErrorCode::InternalError,
+        message: message.to_string(),
+        context: None,
+    }
+}
+
+/// Number of crash reports kept for `TronBackend::crash_report`
+const CRASH_REPORT_LIMIT: usize = 20;
+
+/// Post-mortem details for a panic during compile or execute
+#[derive(Debug, Clone, Serialize)]
+pub struct CrashReport {
+    pub id: String,
+    /// `compile` or `execute`
+    pub stage: String,
+    /// FNV-1a of the source or AST being processed
+    pub script_fingerprint: String,
+    /// Panic message
+    pub message: String,
+    /// Limits in effect at the time
+    pub limits: serde_json::Value,
+}
+
+/// Future adapter that reports a panic while polling as `Err`
+struct CatchUnwind<F>(Pin<Box<F>>);
+
+impl<F: Future> Future for CatchUnwind<F> {
+    type Output = std::thread::Result<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let inner = self.0.as_mut();
+        match std::panic::catch_unwind(AssertUnwindSafe(|| inner.poll(cx))) {
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Ok(Poll::Pending) => Poll::Pending,
+            Err(panic) => Poll::Ready(Err(panic)),
+        }
+    }
+}
+
+/// An error returned to a client, as kept for monitoring
+#[derive(Debug, Clone, Serialize)]
+pub struct RecentError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl TronBackend {
+    /// Create a new TRON backend
+    pub fn new(config: Config) -> Self {
+        Self {
+            ready: std::sync::atomic::AtomicBool::new(false),
+            idempotent_results: Mutex::new(HashMap::new()),
+            recent_errors: Mutex::new(VecDeque::with_capacity(RECENT_ERROR_LIMIT)),
+            crash_reports: Mutex::new(VecDeque::with_capacity(CRASH_REPORT_LIMIT)),
+            crash_sequence: std::sync::atomic::AtomicU64::new(0),
+            native_breaker: CircuitBreaker::new(config.breaker_threshold, config.breaker_cooldown),
+            observer: None,
+            config,
+        }
+    }
+
+    /// Report stage timings to `observer`
+    pub fn with_observer(mut self, observer: Arc<dyn StageObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Initialize the backend
+    pub async fn initialize(&self) -> TronResult<()> {
+        // Initialize native TRON library if available
+        #[cfg(feature = "native")]
+        {
+            // Pre-warm the allocator
+            unsafe {
+                let ctx = ffi::lite3_ctx_create();
+                ffi::lite3_ctx_destroy(ctx);
+            }
+        }
+
+        self.ready
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Exercise encoding, native linkage, signing, attachments and a sample
+    /// compile/execute, returning one entry per check for ops verification.
+    ///
+    /// Checks go through the public entry points, so a configured
+    /// `StageObserver` sees their stages like any other traffic.
+    pub async fn self_test(&self) -> SelfTestReport {
+        let mut checks = Vec::new();
+        let mut record = |name: &str, outcome: TronResult<()>| {
+            checks.push(SelfTestCheck {
+                name: name.to_string(),
+                status: if outcome.is_ok() {
+                    CheckStatus::Pass
+                } else {
+                    CheckStatus::Fail
+                },
+                detail: outcome.err().map(|e| e.to_string()),
+            });
+        };
+
+        let message = TronMessage {
+            header: TronHeader::default(),
+            payload: "toggle .active".to_string(),
+        };
+        let roundtrip = |decoded: TronMessage<String>| {
+            if decoded.payload == message.payload {
+                Ok(())
+            } else {
+                Err(self_test_failure("payload changed in round-trip"))
+            }
+        };
+
+        record(
+            "encode_decode",
+            self.encode(&message)
+                .and_then(|data| self.decode(&data))
+                .and_then(roundtrip),
+        );
+        record(
+            "emulated_encode_decode",
+            self.encode_emulated(&message)
+                .and_then(|data| self.decode_emulated(&data))
+                .and_then(roundtrip),
+        );
+
+        #[cfg(feature = "native")]
+        record(
+            "native_library",
+            // Safety: create/destroy pair with no other use of the context
+            unsafe {
+                let ctx = ffi::lite3_ctx_create();
+                if ctx.is_null() {
+                    Err(self_test_failure("lite3_ctx_create returned null"))
+                } else {
+                    ffi::lite3_ctx_destroy(ctx);
+                    Ok(())
+                }
+            },
+        );
+
+        struct ChecksumSigner;
+        impl Signer for ChecksumSigner {
+            fn sign(&self, data: &[u8]) -> Vec<u8> {
+                fnv1a(&[data]).into_bytes()
+            }
+        }
+        impl Verifier for ChecksumSigner {
+            fn verify(&self, data: &[u8], signature: &[u8]) -> bool {
+                fnv1a(&[data]).as_bytes() == signature
+            }
+        }
+        record(
+            "signing",
+            self.encode_signed(&message, &ChecksumSigner)
+                .and_then(|data| self.decode_verified(&data, &ChecksumSigner))
+                .and_then(roundtrip),
+        );
+
+        record(
+            "attachments",
+            self.encode_with_attachments(&message, &[("probe", b"ok")])
+                .and_then(|data| {
+                    let (decoded, attachments) = self.decode_with_attachments(&data)?;
+                    match attachments.get("probe") {
+                        Some(b"ok") => roundtrip(decoded),
+                        _ => Err(self_test_failure("attachment lost in round-trip")),
+                    }
+                }),
+        );
+
+        record(
+            "compile",
+            self.compile(CompileRequest {
+                source: message.payload.clone(),
+                language: None,
+                options: None,
+            })
+            .await
+            .and_then(|result| verify_provenance(&result)),
+        );
+        record(
+            "execute",
+            self.execute(ExecuteRequest {
+                code: CodeSource::Source(message.payload.clone()),
+                context: None,
+                target: None,
+                idempotency_key: None,
+                dry_run: Some(true),
+                extensions: None,
+            })
+            .await
+            .map(|_| ()),
+        );
+
+        // Configured but not implemented by this adapter yet
+        for (name, enabled) in [
+            ("compression", self.config.compression),
+            ("checksums", self.config.checksums),
+        ] {
+            if enabled {
+                checks.push(SelfTestCheck {
+                    name: name.to_string(),
+                    status: CheckStatus::Skipped,
+                    detail: Some("not implemented by this backend".to_string()),
+                });
+            }
+        }
+
+        SelfTestReport { checks }
+    }
+
+    /// Check if backend is ready
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    /// Remember an error returned to a client
+    pub fn record_error(&self, error: &TronError) {
+        let mut errors = self.recent_errors.lock().unwrap();
+        if errors.len() == RECENT_ERROR_LIMIT {
+            errors.pop_back();
+        }
+        errors.push_front(RecentError {
+            code: error.code,
+            message: error.message.clone(),
+        });
+    }
+
+    /// Look up a crash report referenced by a `TronError.context`
+    pub fn crash_report(&self, id: &str) -> Option<CrashReport> {
+        self.crash_reports
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|report| report.id == id)
+            .cloned()
+    }
+
+    /// Current cache and error statistics
+    pub fn stats(&self) -> BackendStats {
+        BackendStats {
+            ready: self.is_ready(),
+            protocol_version: self.config.protocol_version,
+            idempotency_entries: self.idempotent_results.lock().unwrap().len(),
+            native_breaker: self.native_breaker.state(),
+            recent_errors: self.recent_errors.lock().unwrap().iter().cloned().collect(),
+        }
+    }
+
+    /// Encode a message to TRON format
+    ///
+    /// With the `native` feature, Lite³ failures trip `native_breaker`. While it
+    /// is open (or when a native encode fails) messages fall back to the
+    /// emulated format if `Config.fallback` is `Format::Json`, and otherwise
+    /// fail fast with `ServiceUnavailable`.
+    pub fn encode<T: Serialize>(&self, message: &TronMessage<T>) -> TronResult<Vec<u8>> {
+        let start = Instant::now();
+        let result = self.encode_unobserved(message);
+        self.observe(Stage::Encode, start, &result);
+        result
+    }
+
+    /// Decode a TRON message
+    pub fn decode<T: for<'de> Deserialize<'de>>(&self, data: &[u8]) -> TronResult<TronMessage<T>> {
+        let start = Instant::now();
+        let result = self.decode_unobserved(data);
+        self.observe(Stage::Decode, start, &result);
+        result
+    }
+
+    fn encode_unobserved<T: Serialize>(&self, message: &TronMessage<T>) -> TronResult<Vec<u8>> {
+        #[cfg(feature = "native")]
+        {
+            // Unserializable payloads are the caller's fault, not the library's,
+            // so they fail before the breaker is consulted
+            let payload = Self::native_payload(&message.payload)?;
+            let can_fall_back = self.config.fallback == Some(Format::Json);
+            if self.native_breaker.allow() {
+                match self.encode_native(&message.header, &payload) {
+                    Ok(data) => {
+                        self.native_breaker.record_success();
+                        return Ok(data);
+                    }
+                    Err(e) => {
+                        self.native_breaker.record_failure();
+                        if !can_fall_back {
+                            return Err(e);
+                        }
+                    }
+                }
+            } else if !can_fall_back {
+                return Err(TronError {
+                    code: ErrorCode::ServiceUnavailable,
+                    message: "Native TRON library circuit is open".to_string(),
+                    context: None,
+                });
+            }
+        }
+
+        self.encode_emulated(message)
+    }
+
+    fn decode_unobserved<T: for<'de> Deserialize<'de>>(
         &self,
         data: &[u8],
     ) -> TronResult<TronMessage<T>> {
-        if data.len() < 8 {
-            return Err(TronError {
-                code: ErrorCode::InvalidMessage,
-                message: "Message too short".to_string(),
-                context: None,
-            });
+        // Emulated messages start with the raw magic; they may come from peers
+        // without native support or from the breaker fallback
+        #[cfg(feature = "native")]
+        if !data.starts_with(&TRON_MAGIC.to_be_bytes()) {
+            return self.decode_native(data);
         }
 
-        // Read header
-        let magic = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
-        if magic != TRON_MAGIC {
+        self.decode_emulated(data)
+    }
+
+    /// Encode and sign a message.
+    ///
+    /// Sets `flags::SIGNED` and appends a trailer of the signature followed by
+    /// its length as a big-endian `u16`. The signature covers everything before
+    /// the trailer, header included.
+    pub fn encode_signed<T: Serialize>(
+        &self,
+        message: &TronMessage<T>,
+        signer: &dyn Signer,
+    ) -> TronResult<Vec<u8>> {
+        let signed = TronMessage {
+            header: TronHeader {
+                flags: message.header.flags | flags::SIGNED,
+                ..message.header.clone()
+            },
+            payload: &message.payload,
+        };
+        let mut data = self.encode(&signed)?;
+
+        let signature = signer.sign(&data);
+        let len = u16::try_from(signature.len()).map_err(|_| TronError {
+            code: ErrorCode::InternalError,
+            message: format!("Signature too long: {} bytes", signature.len()),
+            context: None,
+        })?;
+        data.extend_from_slice(&signature);
+        data.extend_from_slice(&len.to_be_bytes());
+        Ok(data)
+    }
+
+    /// Verify the signature trailer and decode the signed message
+    pub fn decode_verified<T: for<'de> Deserialize<'de>>(
+        &self,
+        data: &[u8],
+        verifier: &dyn Verifier,
+    ) -> TronResult<TronMessage<T>> {
+        let invalid = |message: &str| TronError {
+            code: ErrorCode::SignatureInvalid,
+            message: message.to_string(),
+            context: None,
+        };
+
+        let (rest, len) = data
+            .split_last_chunk::<2>()
+            .ok_or_else(|| invalid("Missing signature trailer"))?;
+        let len = u16::from_be_bytes(*len) as usize;
+        let split = rest
+            .len()
+            .checked_sub(len)
+            .ok_or_else(|| invalid("Signature trailer longer than message"))?;
+        let (body, signature) = rest.split_at(split);
+
+        if !verifier.verify(body, signature) {
+            return Err(invalid("Signature verification failed"));
+        }
+
+        let message: TronMessage<T> = self.decode(body)?;
+        if message.header.flags & flags::SIGNED == 0 {
+            return Err(invalid("Signed flag not set in header"));
+        }
+        Ok(message)
+    }
+
+    /// Encode a message followed by binary attachments.
+    ///
+    /// Sets `flags::ATTACHMENTS`. Each attachment is written as a `u16` name
+    /// length, the name, a `u32` data length and the data; the section ends
+    /// with the attachment count (`u16`) and section length (`u32`), all
+    /// big-endian.
+    pub fn encode_with_attachments<T: Serialize>(
+        &self,
+        message: &TronMessage<T>,
+        attachments: &[(&str, &[u8])],
+    ) -> TronResult<Vec<u8>> {
+        let too_large = |what: &str| TronError {
+            code: ErrorCode::InternalError,
+            message: format!("Attachment {} too large", what),
+            context: None,
+        };
+
+        let tagged = TronMessage {
+            header: TronHeader {
+                flags: message.header.flags | flags::ATTACHMENTS,
+                ..message.header.clone()
+            },
+            payload: &message.payload,
+        };
+        let mut data = self.encode(&tagged)?;
+
+        let section_start = data.len();
+        for (name, blob) in attachments {
+            let name_len = u16::try_from(name.len()).map_err(|_| too_large("name"))?;
+            let blob_len = u32::try_from(blob.len()).map_err(|_| too_large("data"))?;
+            data.extend_from_slice(&name_len.to_be_bytes());
+            data.extend_from_slice(name.as_bytes());
+            data.extend_from_slice(&blob_len.to_be_bytes());
+            data.extend_from_slice(blob);
+        }
+        let count = u16::try_from(attachments.len()).map_err(|_| too_large("count"))?;
+        let section_len =
+            u32::try_from(data.len() - section_start).map_err(|_| too_large("section"))?;
+        data.extend_from_slice(&count.to_be_bytes());
+        data.extend_from_slice(&section_len.to_be_bytes());
+
+        if data.len() > self.config.max_message_size {
             return Err(TronError {
                 code: ErrorCode::InvalidMessage,
-                message: format!("Invalid magic: 0x{:08X}", magic),
+                message: format!(
+                    "Message with attachments exceeds {} bytes",
+                    self.config.max_message_size
+                ),
                 context: None,
             });
         }
+        Ok(data)
+    }
+
+    /// Decode a message and index its attachments without copying them
+    pub fn decode_with_attachments<'a, T: for<'de> Deserialize<'de>>(
+        &self,
+        data: &'a [u8],
+    ) -> TronResult<(TronMessage<T>, Attachments<'a>)> {
+        let (rest, footer) = data
+            .split_last_chunk::<6>()
+            .ok_or_else(|| invalid_attachments("Missing attachment footer"))?;
+        let count = u16::from_be_bytes([footer[0], footer[1]]);
+        let section_len = u32::from_be_bytes([footer[2], footer[3], footer[4], footer[5]]) as usize;
+        let split = rest
+            .len()
+            .checked_sub(section_len)
+            .ok_or_else(|| invalid_attachments("Attachment section longer than message"))?;
+        let (body, section) = rest.split_at(split);
+
+        let message: TronMessage<T> = self.decode(body)?;
+        if message.header.flags & flags::ATTACHMENTS == 0 {
+            return Err(invalid_attachments("Attachments flag not set in header"));
+        }
+
+        let attachments = Attachments { section, count };
+        // Validate the layout up front so lazy access can't fail later
+        for entry in attachments.entries() {
+            entry?;
+        }
+        Ok((message, attachments))
+    }
+
+    /// Compile hyperscript source
+    ///
+    /// Errors are localized for `CompileRequest::locale`.
+    pub async fn compile(&self, request: CompileRequest) -> TronResult<CompileResult> {
+        let start = Instant::now();
+        let fingerprint = fnv1a(&[request.source.as_bytes()]);
+        let locale = request.locale().map(str::to_owned);
+        let result = self
+            .catch_panics("compile", fingerprint, self.compile_unguarded(request))
+            .await
+            .map_err(|e| e.localize(locale.as_deref()));
+        self.observe(Stage::Compile, start, &result);
+        result
+    }
+
+    /// Compile source read from an async stream.
+    ///
+    /// Input is read in bounded chunks and rejected once it exceeds
+    /// `Config.max_source_bytes`, without buffering the rest. The source is
+    /// still compiled as a whole; tokenizing while bytes arrive waits on a Rust
+    /// tokenizer.
+    pub async fn compile_stream<R: AsyncRead + Unpin>(
+        &self,
+        reader: R,
+        language: Option<String>,
+        options: Option<CompileOptions>,
+    ) -> TronResult<CompileResult> {
+        let limit = self.config.max_source_bytes;
+        let mut buffer = Vec::new();
+        reader
+            .take(limit as u64 + 1)
+            .read_to_end(&mut buffer)
+            .await
+            .map_err(|e| TronError {
+                code: ErrorCode::InvalidMessage,
+                message: format!("Failed to read source stream: {}", e),
+                context: None,
+            })?;
+
+        if buffer.len() > limit {
+            return Err(TronError {
+                code: ErrorCode::InvalidMessage,
+                message: format!("Source stream exceeds {} bytes", limit),
+                // Same shape as `compile`; the full size is never read
+                context: Some(serde_json::json!({
+                    "limit": "max_source_bytes",
+                    "max": limit,
+                })),
+            });
+        }
+
+        let source = String::from_utf8(buffer).map_err(|e| TronError {
+            code: ErrorCode::InvalidMessage,
+            message: format!("Source stream is not valid UTF-8: {}", e),
+            context: None,
+        })?;
+
+        self.compile(CompileRequest {
+            source,
+            language,
+            options,
+        })
+        .await
+    }
+
+    /// Execute hyperscript
+    pub async fn execute(&self, request: ExecuteRequest) -> TronResult<ExecuteResult> {
+        // Dry runs perform nothing, so they neither replay nor store results
+        let dry_run = request.dry_run == Some(true);
+        let in_flight = match &request.idempotency_key {
+            Some(key) if !dry_run => match self.claim_idempotency_key(key, &request).await? {
+                Claim::Replay(mut cached) => {
+                    cached.meta.replayed = Some(true);
+                    return Ok(cached);
+                }
+                Claim::Execute(guard) => Some(guard),
+            },
+            _ => None,
+        };
+
+        let fingerprint = match &request.code {
+            CodeSource::Source(source) => fnv1a(&[source.as_bytes()]),
+            CodeSource::Ast(ast) => fnv1a(&[ast]),
+        };
+        let start = Instant::now();
+        let result = self
+            .catch_panics("execute", fingerprint, self.execute_uncached(request))
+            .await;
+        self.observe(Stage::Execute, start, &result);
+        let result = result?;
+
+        if let Some(guard) = in_flight {
+            guard.complete(&result);
+        }
+        Ok(result)
+    }
+
+    // -------------------------------------------------------------------------
+    // Private Methods
+    // -------------------------------------------------------------------------
+
+    fn observe<R>(&self, stage: Stage, start: Instant, result: &TronResult<R>) {
+        if let Some(observer) = &self.observer {
+            observer.on_stage(stage, start.elapsed(), result.is_ok());
+        }
+    }
+
+    async fn compile_unguarded(&self, request: CompileRequest) -> TronResult<CompileResult> {
+        let start = Instant::now();
+        self.check_source_size(&request.source)?;
+
+        let grammar_version = request
+            .options
+            .as_ref()
+            .and_then(|o| o.grammar_version)
+            .unwrap_or(GRAMMAR_VERSION);
+        if !(MIN_GRAMMAR_VERSION..=GRAMMAR_VERSION).contains(&grammar_version) {
+            return Err(TronError {
+                code: ErrorCode::UnsupportedVersion,
+                message: format!(
+                    "Unsupported grammar version {} (supported: {}-{})",
+                    grammar_version, MIN_GRAMMAR_VERSION, GRAMMAR_VERSION
+                ),
+                context: None,
+            });
+        }
+
+        // TODO: Integrate with HyperFixi WASM module
+        // For now, return a placeholder
+
+        let warnings = request
+            .options
+            .as_ref()
+            .and_then(|o| o.dialect)
+            .map(|dialect| {
+                vec![format!(
+                    "Dialect {:?} is not enforced yet; legacy constructs are not checked",
+                    dialect
+                )]
+            });
+
+        Ok(CompileResult {
+            schema_version: RESULT_SCHEMA_VERSION,
+            ast: Vec::new(),
+            meta: CompileMeta {
+                parser_used: Some("placeholder".to_string()),
+                warnings,
+                compile_time_ms: Some(start.elapsed().as_secs_f64() * 1000.0),
+                grammar_version: Some(grammar_version),
+                provenance: Some(Provenance::current(
+                    grammar_version,
+                    request.options.as_ref(),
+                )),
+                ..Default::default()
+            },
+            source_map: None,
+        })
+    }
+
+    /// Enforce `Config.max_source_bytes` on hyperscript source
+    fn check_source_size(&self, source: &str) -> TronResult<()> {
+        if source.len() > self.config.max_source_bytes {
+            return Err(TronError {
+                code: ErrorCode::InvalidMessage,
+                message: format!(
+                    "Source is {} bytes, limit is {}",
+                    source.len(),
+                    self.config.max_source_bytes
+                ),
+                context: Some(serde_json::json!({
+                    "limit": "max_source_bytes",
+                    "max": self.config.max_source_bytes,
+                    "actual": source.len(),
+                })),
+            });
+        }
+        Ok(())
+    }
+
+    async fn execute_uncached(&self, request: ExecuteRequest) -> TronResult<ExecuteResult> {
+        let start = Instant::now();
+        if let CodeSource::Source(source) = &request.code {
+            self.check_source_size(source)?;
+        }
+
+        // TODO: Integrate with HyperFixi WASM module
+        // Dry runs get an empty plan until commands report their effects
+        let plan = request.dry_run.unwrap_or(false).then(EffectPlan::default);
+
+        Ok(ExecuteResult {
+            schema_version: RESULT_SCHEMA_VERSION,
+            success: true,
+            value: None,
+            context: request.context,
+            plan,
+            meta: ExecuteMeta {
+                execution_time_ms: Some(start.elapsed().as_secs_f64() * 1000.0),
+                commands_executed: Some(1),
+                ..Default::default()
+            },
+        })
+    }
+
+    /// Turn a panic inside `future` into an `InternalError` referencing a
+    /// stored `CrashReport`
+    async fn catch_panics<T>(
+        &self,
+        stage: &str,
+        script_fingerprint: String,
+        future: impl Future<Output = TronResult<T>>,
+    ) -> TronResult<T> {
+        let panic = match CatchUnwind(Box::pin(future)).await {
+            Ok(result) => return result,
+            Err(panic) => panic,
+        };
+
+        let message = panic
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "non-string panic payload".to_string());
+        let sequence = self
+            .crash_sequence
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        let report = CrashReport {
+            id: format!("crash-{}-{}", script_fingerprint, sequence),
+            stage: stage.to_string(),
+            script_fingerprint,
+            message,
+            limits: serde_json::json!({
+                "max_source_bytes": self.config.max_source_bytes,
+                "max_message_size": self.config.max_message_size,
+                "timeout_ms": self.config.timeout.as_millis() as u64,
+            }),
+        };
+
+        let error = TronError {
+            code: ErrorCode::InternalError,
+            message: format!("Internal error during {}", stage),
+            context: Some(serde_json::json!({ "crash_report": report.id })),
+        };
+
+        let mut reports = self.crash_reports.lock().unwrap();
+        if reports.len() == CRASH_REPORT_LIMIT {
+            reports.pop_back();
+        }
+        reports.push_front(report);
+        Err(error)
+    }
+
+    /// Replay the stored result for `key`, wait for an in-flight execution of
+    /// the same request, or mark the key in flight for the caller to execute
+    async fn claim_idempotency_key(
+        &self,
+        key: &str,
+        request: &ExecuteRequest,
+    ) -> TronResult<Claim<'_>> {
+        let fingerprint = serde_json::to_vec(&ExecuteRequest {
+            idempotency_key: None,
+            ..request.clone()
+        })
+        .map(|bytes| fnv1a(&[&bytes]))
+        .map_err(|e| TronError {
+            code: ErrorCode::InternalError,
+            message: format!("Failed to fingerprint request: {}", e),
+            context: None,
+        })?;
+
+        loop {
+            let notify;
+            let notified;
+            {
+                let window = self.config.idempotency_window;
+                let mut results = self.idempotent_results.lock().unwrap();
+                results.retain(|_, entry| {
+                    matches!(entry.state, IdempotentState::InFlight(_))
+                        || entry.stored_at.elapsed() < window
+                });
+
+                match results.get(key) {
+                    Some(entry) if entry.fingerprint != fingerprint => {
+                        return Err(TronError {
+                            code: ErrorCode::InvalidMessage,
+                            message: format!(
+                                "Idempotency key `{}` was already used for a different request",
+                                key
+                            ),
+                            context: Some(serde_json::json!({ "idempotency_key": key })),
+                        });
+                    }
+                    Some(IdempotentEntry {
+                        state: IdempotentState::Done(result),
+                        ..
+                    }) => return Ok(Claim::Replay(result.clone())),
+                    Some(IdempotentEntry {
+                        state: IdempotentState::InFlight(pending),
+                        ..
+                    }) => {
+                        // Register before unlocking so the wakeup can't be missed
+                        notify = pending.clone();
+                        notified = notify.notified();
+                    }
+                    None => {
+                        if results.len() >= self.config.max_idempotency_entries {
+                            let oldest = results
+                                .iter()
+                                .filter(|(_, entry)| {
+                                    matches!(entry.state, IdempotentState::Done(_))
+                                })
+                                .min_by_key(|(_, entry)| entry.stored_at)
+                                .map(|(key, _)| key.clone());
+                            match oldest {
+                                Some(oldest) => {
+                                    results.remove(&oldest);
+                                }
+                                None => {
+                                    return Err(TronError {
+                                        code: ErrorCode::ServiceUnavailable,
+                                        message: "Too many idempotent executions in flight"
+                                            .to_string(),
+                                        context: None,
+                                    });
+                                }
+                            }
+                        }
+
+                        let notify = Arc::new(tokio::sync::Notify::new());
+                        results.insert(
+                            key.to_string(),
+                            IdempotentEntry {
+                                fingerprint,
+                                stored_at: Instant::now(),
+                                state: IdempotentState::InFlight(notify.clone()),
+                            },
+                        );
+                        return Ok(Claim::Execute(InFlightGuard {
+                            results: &self.idempotent_results,
+                            key: key.to_string(),
+                            notify,
+                        }));
+                    }
+                }
+            }
+            notified.await;
+        }
+    }
+
+    #[cfg(feature = "native")]
+    fn native_payload<T: Serialize>(payload: &T) -> TronResult<std::ffi::CString> {
+        let payload_json = serde_json::to_string(payload).map_err(|e| TronError {
+            code: ErrorCode::InternalError,
+            message: format!("Failed to serialize payload: {}", e),
+            context: None,
+        })?;
+        std::ffi::CString::new(payload_json).map_err(|_| TronError {
+            code: ErrorCode::InternalError,
+            message: "Payload contains a NUL byte".to_string(),
+            context: None,
+        })
+    }
+
+    /// Errors from here are Lite³ failures only; the payload is already
+    /// serialized so an error can't leak the context
+    #[cfg(feature = "native")]
+    fn encode_native(&self, header: &TronHeader, payload: &std::ffi::CStr) -> TronResult<Vec<u8>> {
+        use std::ffi::CString;
+
+        let native_failure = |what: &str| TronError {
+            code: ErrorCode::ServiceUnavailable,
+            message: format!("Lite³ {} failed", what),
+            context: None,
+        };
+
+        unsafe {
+            let ctx = ffi::lite3_ctx_create();
+            if ctx.is_null() {
+                return Err(native_failure("context allocation"));
+            }
+            ffi::lite3_ctx_init_obj(ctx);
+
+            // Set header
+            let magic_key = CString::new("magic").unwrap();
+            ffi::lite3_ctx_set_i64(ctx, 0, magic_key.as_ptr(), header.magic as i64);
+
+            let version_key = CString::new("version").unwrap();
+            ffi::lite3_ctx_set_i64(ctx, 0, version_key.as_ptr(), header.version as i64);
+
+            let flags_key = CString::new("flags").unwrap();
+            ffi::lite3_ctx_set_i64(ctx, 0, flags_key.as_ptr(), header.flags as i64);
+
+            // Payload
+            let payload_key = CString::new("payload").unwrap();
+            ffi::lite3_ctx_set_str(ctx, 0, payload_key.as_ptr(), payload.as_ptr());
+
+            // Get buffer
+            let mut len: usize = 0;
+            let buf_ptr = ffi::lite3_ctx_get_buffer(ctx, &mut len);
+            if buf_ptr.is_null() {
+                ffi::lite3_ctx_destroy(ctx);
+                return Err(native_failure("buffer export"));
+            }
+            let result = std::slice::from_raw_parts(buf_ptr as *const u8, len).to_vec();
+
+            ffi::lite3_ctx_destroy(ctx);
+
+            Ok(result)
+        }
+    }
+
+    fn encode_emulated<T: Serialize>(&self, message: &TronMessage<T>) -> TronResult<Vec<u8>> {
+        // JSON-based emulation for development
+        let json = serde_json::to_vec(message).map_err(|e| TronError {
+            code: ErrorCode::InternalError,
+            message: format!("Failed to encode: {}", e),
+            context: None,
+        })?;
+
+        // Prepend header (8 bytes)
+        let mut result = Vec::with_capacity(8 + json.len());
+
+        // Magic (4 bytes, big-endian)
+        result.extend_from_slice(&message.header.magic.to_be_bytes());
+        // Version (2 bytes, big-endian)
+        result.extend_from_slice(&message.header.version.to_be_bytes());
+        // Flags (2 bytes, big-endian)
+        result.extend_from_slice(&message.header.flags.to_be_bytes());
+        // Payload
+        result.extend_from_slice(&json);
+
+        Ok(result)
+    }
+
+    #[cfg(feature = "native")]
+    fn decode_native<T: for<'de> Deserialize<'de>>(
+        &self,
+        data: &[u8],
+    ) -> TronResult<TronMessage<T>> {
+        use std::ffi::{CStr, CString};
+
+        let invalid = |message: String| TronError {
+            code: ErrorCode::InvalidMessage,
+            message,
+            context: None,
+        };
+
+        unsafe {
+            let ctx = ffi::lite3_ctx_from_buffer(data.as_ptr() as *const _, data.len());
+            if ctx.is_null() {
+                return Err(invalid("Not a Lite³ document".to_string()));
+            }
+
+            let get_i64 = |key: &str| {
+                let key = CString::new(key).unwrap();
+                let mut value = 0i64;
+                match ffi::lite3_ctx_get_i64(ctx, 0, key.as_ptr(), &mut value) {
+                    0 => Ok(value),
+                    _ => Err(invalid(format!(
+                        "Missing integer field `{}`",
+                        key.to_string_lossy()
+                    ))),
+                }
+            };
+            let header = (|| {
+                let magic = get_i64("magic")?;
+                let version = get_i64("version")?;
+                let flags = get_i64("flags")?;
+                if magic != TRON_MAGIC as i64 {
+                    return Err(invalid(format!("Invalid magic: 0x{:08X}", magic)));
+                }
+                Ok(TronHeader {
+                    magic: TRON_MAGIC,
+                    version: u16::try_from(version)
+                        .map_err(|_| invalid(format!("Invalid version: {}", version)))?,
+                    flags: u16::try_from(flags)
+                        .map_err(|_| invalid(format!("Invalid flags: {}", flags)))?,
+                })
+            })();
+
+            // The payload is stored as JSON text by `encode_native`; copy it out
+            // before the context (which owns the string) is destroyed. Structured
+            // payload objects would need a key iterator the FFI doesn't expose.
+            let payload_key = CString::new("payload").unwrap();
+            let payload_ptr = ffi::lite3_ctx_get_str(ctx, 0, payload_key.as_ptr());
+            let payload_json = if !payload_ptr.is_null() {
+                Ok(CStr::from_ptr(payload_ptr).to_bytes().to_vec())
+            } else if ffi::lite3_ctx_get_obj(ctx, 0, payload_key.as_ptr()) >= 0 {
+                Err(invalid(
+                    "Structured `payload` objects are not supported; expected JSON text"
+                        .to_string(),
+                ))
+            } else {
+                Err(invalid("Missing string field `payload`".to_string()))
+            };
+            ffi::lite3_ctx_destroy(ctx);
+
+            let header = header?;
+            let payload_json = payload_json?;
+            let payload = serde_json::from_slice(&payload_json)
+                .map_err(|e| invalid(format!("Failed to decode payload: {}", e)))?;
+
+            Ok(TronMessage { header, payload })
+        }
+    }
+
+    fn decode_emulated<T: for<'de> Deserialize<'de>>(
+        &self,
+        data: &[u8],
+    ) -> TronResult<TronMessage<T>> {
+        if data.len() < 8 {
+            return Err(TronError {
+                code: ErrorCode::InvalidMessage,
+                message: "Message too short".to_string(),
+                context: None,
+            });
+        }
+
+        // Read header
+        let magic = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+        if magic != TRON_MAGIC {
+            return Err(TronError {
+                code: ErrorCode::InvalidMessage,
+                message: format!("Invalid magic: 0x{:08X}", magic),
+                context: None,
+            });
+        }
+
+        let version = u16::from_be_bytes([data[4], data[5]]);
+        let flags = u16::from_be_bytes([data[6], data[7]]);
+
+        // Parse JSON envelope; the prefix is authoritative for the header
+        let envelope: EmulatedEnvelope<T> =
+            serde_json::from_slice(&data[8..]).map_err(|e| TronError {
+                code: ErrorCode::InvalidMessage,
+                message: format!("Failed to decode payload: {}", e),
+                context: None,
+            })?;
+        let payload = envelope.payload;
+
+        Ok(TronMessage {
+            header: TronHeader {
+                magic,
+                version,
+                flags,
+            },
+            payload,
+        })
+    }
+}
+
+// =============================================================================
+// Attachments
+// =============================================================================
+
+/// Attachments borrowed from a decoded message buffer
+#[derive(Debug, Clone, Copy)]
+pub struct Attachments<'a> {
+    section: &'a [u8],
+    count: u16,
+}
+
+impl<'a> Attachments<'a> {
+    pub fn len(&self) -> usize {
+        self.count as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Look up an attachment by name
+    pub fn get(&self, name: &str) -> Option<&'a [u8]> {
+        self.iter()
+            .find(|(entry, _)| *entry == name)
+            .map(|(_, data)| data)
+    }
+
+    /// Iterate over `(name, data)` pairs in encoding order
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, &'a [u8])> {
+        self.entries().map_while(Result::ok)
+    }
+
+    fn entries(&self) -> impl Iterator<Item = TronResult<(&'a str, &'a [u8])>> {
+        let mut rest = self.section;
+        (0..self.count).map(move |_| {
+            let name = take_prefixed(&mut rest, 2)?;
+            let name = std::str::from_utf8(name)
+                .map_err(|_| invalid_attachments("Attachment name is not UTF-8"))?;
+            let data = take_prefixed(&mut rest, 4)?;
+            Ok((name, data))
+        })
+    }
+}
+
+/// Split a big-endian length-prefixed slice off the front of `rest`
+fn take_prefixed<'a>(rest: &mut &'a [u8], prefix: usize) -> TronResult<&'a [u8]> {
+    if rest.len() < prefix {
+        return Err(invalid_attachments("Truncated attachment"));
+    }
+    let (len, tail) = rest.split_at(prefix);
+    let len = len.iter().fold(0usize, |acc, b| (acc << 8) | *b as usize);
+    if tail.len() < len {
+        return Err(invalid_attachments("Truncated attachment"));
+    }
+    let (value, tail) = tail.split_at(len);
+    *rest = tail;
+    Ok(value)
+}
+
+fn invalid_attachments(message: &str) -> TronError {
+    TronError {
+        code: ErrorCode::InvalidMessage,
+        message: message.to_string(),
+        context: None,
+    }
+}
+
+// =============================================================================
+// Protocol Compatibility
+// =============================================================================
+
+/// Checks for decoding messages written by earlier versions of this crate.
+///
+/// Downstream integration tests keep encoded fixtures from past releases and
+/// run them through `check` to catch breaking protocol changes.
+pub mod compat {
+    use super::*;
+
+    /// Outcome of checking one stored message
+    #[derive(Debug, Clone, Default)]
+    pub struct Report {
+        /// Header version of the stored message, if the header was readable
+        pub version: Option<u16>,
+        pub issues: Vec<Issue>,
+    }
+
+    /// A compatibility problem found in a stored message
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Issue {
+        /// Stored message is newer than the backend's protocol version
+        NewerVersion { stored: u16, supported: u16 },
+        /// The current decoder rejects the message
+        DecodeFailed(String),
+        /// A field present in the stored payload is ignored by the current types
+        DroppedField(String),
+    }
+
+    impl Report {
+        /// True when the message decodes without losing data
+        pub fn is_compatible(&self) -> bool {
+            self.issues.is_empty()
+        }
+    }
+
+    /// Decode `old_bytes` as a `T` with the backend's current decoder and
+    /// report anything that would break or silently lose data.
+    pub fn check<T>(backend: &TronBackend, old_bytes: &[u8]) -> Report
+    where
+        T: Serialize + for<'de> Deserialize<'de>,
+    {
+        let mut report = Report::default();
+
+        let decoded = match backend.decode::<T>(old_bytes) {
+            Ok(message) => message,
+            Err(e) => {
+                report.issues.push(Issue::DecodeFailed(e.message));
+                return report;
+            }
+        };
+
+        let stored = decoded.header.version;
+        let supported = backend.config.protocol_version;
+        report.version = Some(stored);
+        if stored > supported {
+            report
+                .issues
+                .push(Issue::NewerVersion { stored, supported });
+        }
+
+        // Compare against the raw payload to spot fields the decoder dropped;
+        // decoding through the backend handles every wire format it accepts
+        let original = match backend.decode::<serde_json::Value>(old_bytes) {
+            Ok(message) => message.payload,
+            Err(e) => {
+                report.issues.push(Issue::DecodeFailed(e.message));
+                return report;
+            }
+        };
+        match serde_json::to_value(&decoded.payload) {
+            Ok(current) => dropped_fields(&original, &current, "", &mut report.issues),
+            Err(e) => report.issues.push(Issue::DecodeFailed(e.to_string())),
+        }
+
+        report
+    }
+
+    fn dropped_fields(
+        original: &serde_json::Value,
+        current: &serde_json::Value,
+        path: &str,
+        issues: &mut Vec<Issue>,
+    ) {
+        use serde_json::Value;
+
+        if let (Value::Object(original), Value::Object(current)) = (original, current) {
+            for (key, value) in original {
+                let field = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                match current.get(key) {
+                    Some(current) => dropped_fields(value, current, &field, issues),
+                    // Absent nulls are just skipped `None`s
+                    None if !value.is_null() => issues.push(Issue::DroppedField(field)),
+                    None => {}
+                }
+            }
+        }
+    }
+}
+
+// =============================================================================
+// Axum Integration
+// =============================================================================
+
+#[cfg(feature = "axum")]
+pub mod axum_integration {
+    use super::*;
+    use axum::{
+        extract::State,
+        http::{header, HeaderMap, StatusCode},
+        response::{IntoResponse, Response},
+        Json,
+    };
+
+    /// Axum state wrapper
+    pub type TronState = Arc<TronBackend>;
+
+    /// Compile handler for Axum
+    ///
+    /// Responses carry a weak `ETag` from `CompileResult::fingerprint` (the body
+    /// still includes `compile_time_ms`); a matching `If-None-Match`, compared
+    /// weakly, gets `304 Not Modified` with no body.
+    pub async fn compile_handler(
+        State(backend): State<TronState>,
+        headers: HeaderMap,
+        Json(request): Json<CompileRequest>,
+    ) -> Result<Response, TronErrorResponse> {
+        let result = backend.compile(request).await.map_err(|e| {
+            backend.record_error(&e);
+            TronErrorResponse(e)
+        })?;
+
+        let opaque = format!("\"{}\"", result.fingerprint());
+        let not_modified = headers
+            .get_all(header::IF_NONE_MATCH)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|tag| {
+                let tag = tag.trim();
+                tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == opaque
+            });
+        let etag = format!("W/{}", opaque);
+
+        let etag_header = [(header::ETAG, etag)];
+        if not_modified {
+            Ok((StatusCode::NOT_MODIFIED, etag_header).into_response())
+        } else {
+            Ok((etag_header, Json(result)).into_response())
+        }
+    }
+
+    /// Execute handler for Axum
+    pub async fn execute_handler(
+        State(backend): State<TronState>,
+        Json(request): Json<ExecuteRequest>,
+    ) -> Result<Json<ExecuteResult>, TronErrorResponse> {
+        backend.execute(request).await.map(Json).map_err(|e| {
+            backend.record_error(&e);
+            TronErrorResponse(e)
+        })
+    }
+
+    /// Largest script accepted by `playground_execute_handler`
+    pub const PLAYGROUND_MAX_SOURCE_BYTES: usize = 4 * 1024;
+
+    /// Time budget for `playground_execute_handler`
+    pub const PLAYGROUND_TIMEOUT: Duration = Duration::from_millis(500);
+
+    /// Execute handler for public playgrounds.
+    ///
+    /// Always runs as a dry run, regardless of server config: side effects come
+    /// back as an `EffectPlan`, idempotency and plugin extensions are ignored,
+    /// and scripts are held to `PLAYGROUND_MAX_SOURCE_BYTES` and
+    /// `PLAYGROUND_TIMEOUT`.
+    pub async fn playground_execute_handler(
+        State(backend): State<TronState>,
+        Json(mut request): Json<ExecuteRequest>,
+    ) -> Result<Json<ExecuteResult>, TronErrorResponse> {
+        let size = match &request.code {
+            CodeSource::Source(source) => source.len(),
+            CodeSource::Ast(ast) => ast.len(),
+        };
+        let result = if size > PLAYGROUND_MAX_SOURCE_BYTES {
+            Err(TronError {
+                code: ErrorCode::InvalidMessage,
+                message: format!(
+                    "Playground scripts are limited to {} bytes",
+                    PLAYGROUND_MAX_SOURCE_BYTES
+                ),
+                context: None,
+            })
+        } else {
+            request.dry_run = Some(true);
+            request.idempotency_key = None;
+            request.extensions = None;
+
+            tokio::time::timeout(PLAYGROUND_TIMEOUT, backend.execute(request))
+                .await
+                .unwrap_or_else(|_| {
+                    Err(TronError {
+                        code: ErrorCode::Timeout,
+                        message: format!(
+                            "Playground execution exceeded {}ms",
+                            PLAYGROUND_TIMEOUT.as_millis()
+                        ),
+                        context: None,
+                    })
+                })
+        };
+
+        result.map(Json).map_err(|e| {
+            backend.record_error(&e);
+            TronErrorResponse(e)
+        })
+    }
+
+    /// Admin page with backend stats, recent errors and a compile/execute
+    /// playground. Mount it behind your own authentication.
+    #[cfg(feature = "admin")]
+    pub async fn admin_handler(State(backend): State<TronState>) -> axum::response::Html<String> {
+        let stats = backend.stats();
+
+        let errors = if stats.recent_errors.is_empty() {
+            "<li>None</li>".to_string()
+        } else {
+            stats
+                .recent_errors
+                .iter()
+                .map(|e| {
+                    format!(
+                        "<li><code>{:?}</code> {}</li>",
+                        e.code,
+                        escape_html(&e.message)
+                    )
+                })
+                .collect()
+        };
+
+        axum::response::Html(format!(
+            include_str!("admin.html"),
+            ready = stats.ready,
+            protocol_version = stats.protocol_version,
+            idempotency_entries = stats.idempotency_entries,
+            errors = errors,
+        ))
+    }
+
+    #[cfg(feature = "admin")]
+    fn escape_html(text: &str) -> String {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    }
+
+    /// Error response wrapper
+    pub struct TronErrorResponse(pub TronError);
+
+    impl IntoResponse for TronErrorResponse {
+        fn into_response(self) -> Response {
+            let status = match self.0.code {
+                ErrorCode::InvalidMessage | ErrorCode::InvalidPayloadType => StatusCode::BAD_REQUEST,
+                ErrorCode::UnsupportedVersion | ErrorCode::UnsupportedLanguage => {
+                    StatusCode::NOT_IMPLEMENTED
+                }
+                ErrorCode::SignatureInvalid => StatusCode::UNAUTHORIZED,
+                ErrorCode::ParseError | ErrorCode::SyntaxError => StatusCode::UNPROCESSABLE_ENTITY,
+                ErrorCode::RuntimeError => StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorCode::Timeout => StatusCode::REQUEST_TIMEOUT,
+                ErrorCode::ServiceUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+
+            (status, Json(self.0)).into_response()
+        }
+    }
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tron_header_default() {
+        let header = TronHeader::default();
+        assert_eq!(header.magic, TRON_MAGIC);
+        assert_eq!(header.version, 1);
+        assert_eq!(header.flags, 0);
+    }
+
+    #[test]
+    fn test_encode_decode_roundtrip() {
+        let backend = TronBackend::new(Config::default());
+
+        let message = TronMessage {
+            header: TronHeader::default(),
+            payload: CompileRequest {
+                source: "toggle .active".to_string(),
+                language: Some("en".to_string()),
+                options: None,
+            },
+        };
+
+        let encoded = backend.encode(&message).unwrap();
+        let decoded: TronMessage<CompileRequest> = backend.decode(&encoded).unwrap();
+
+        assert_eq!(decoded.header.magic, TRON_MAGIC);
+        assert_eq!(decoded.payload.source, "toggle .active");
+    }
+
+    #[test]
+    fn test_emulated_format_matches_node_adapter() {
+        let backend = TronBackend::new(Config::default());
+
+        // Written by the Node adapter and by earlier releases of this one
+        let mut data = Vec::new();
+        data.extend_from_slice(&TRON_MAGIC.to_be_bytes());
+        data.extend_from_slice(&1u16.to_be_bytes());
+        data.extend_from_slice(&0u16.to_be_bytes());
+        data.extend_from_slice(
+            br#"{"header":{"magic":1414680142,"version":1,"flags":0},"payload":{"source":"toggle .active"}}"#,
+        );
+        let decoded: TronMessage<CompileRequest> = backend.decode(&data).unwrap();
+        assert_eq!(decoded.payload.source, "toggle .active");
+
+        let message = TronMessage {
+            header: TronHeader::default(),
+            payload: "toggle .active".to_string(),
+        };
+        let encoded = backend.encode_emulated(&message).unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&encoded[8..]).unwrap();
+        assert_eq!(body["header"]["magic"], TRON_MAGIC);
+        assert_eq!(body["payload"], "toggle .active");
+    }
+
+    #[test]
+    fn test_extensions_survive_encoding() {
+        let backend = TronBackend::new(Config::default());
+
+        let mut extensions = Extensions::default();
+        extensions.insert("analytics.sample_rate", &0.25).unwrap();
+
+        let message = TronMessage {
+            header: TronHeader::default(),
+            payload: CompileRequest {
+                source: "toggle .active".to_string(),
+                language: None,
+                options: Some(CompileOptions {
+                    extensions: Some(extensions),
+                    ..Default::default()
+                }),
+            },
+        };
+
+        let encoded = backend.encode(&message).unwrap();
+        let decoded: TronMessage<CompileRequest> = backend.decode(&encoded).unwrap();
+        let extensions = decoded.payload.options.unwrap().extensions.unwrap();
+
+        assert_eq!(extensions.version, EXTENSIONS_VERSION);
+        assert_eq!(
+            extensions.get::<f64>("analytics.sample_rate").unwrap(),
+            Some(0.25)
+        );
+        assert_eq!(extensions.get::<f64>("missing").unwrap(), None);
+    }
+
+    #[test]
+    fn test_compat_check_reports_dropped_fields() {
+        let backend = TronBackend::new(Config::default());
+
+        let mut stored = Vec::new();
+        stored.extend_from_slice(&TRON_MAGIC.to_be_bytes());
+        stored.extend_from_slice(&1u16.to_be_bytes());
+        stored.extend_from_slice(&0u16.to_be_bytes());
+        stored.extend_from_slice(
+            br#"{"header":{"magic":1414680142,"version":1,"flags":0},"payload":{"source":"toggle .active","language":null,"options":{"semantic":true,"legacy_mode":"v0"}}}"#,
+        );
+
+        let report = compat::check::<CompileRequest>(&backend, &stored);
+        assert_eq!(report.version, Some(1));
+        assert_eq!(
+            report.issues,
+            vec![compat::Issue::DroppedField(
+                "options.legacy_mode".to_string()
+            )]
+        );
+
+        let report = compat::check::<CompileRequest>(&backend, b"JSON{}");
+        assert!(!report.is_compatible());
+    }
+
+    #[test]
+    fn test_compat_check_accepts_previous_release() {
+        let backend = TronBackend::new(Config::default());
+
+        // Bytes from the emulated encoder as released before protocol changes
+        let fixture = [
+            b"TRON\x00\x01\x00\x00".as_slice(),
+            br#"{"header":{"magic":1414680142,"version":1,"flags":0},"#,
+            br#""payload":{"source":"toggle .active","language":"en","#,
+            br#""options":{"semantic":true,"confidence_threshold":0.8,"target":"browser"}}}"#,
+        ]
+        .concat();
+
+        let report = compat::check::<CompileRequest>(&backend, &fixture);
+        assert_eq!(report.version, Some(1));
+        assert!(report.is_compatible(), "{:?}", report.issues);
+    }
+
+    /// Toy keyed checksum so the trailer logic can be tested without a crypto
+    /// dependency
+    struct SumSigner(u8);
+
+    impl Signer for SumSigner {
+        fn sign(&self, data: &[u8]) -> Vec<u8> {
+            let sum = data.iter().fold(self.0, |acc, b| acc.wrapping_add(*b));
+            vec![sum; 4]
+        }
+    }
+
+    impl Verifier for SumSigner {
+        fn verify(&self, data: &[u8], signature: &[u8]) -> bool {
+            self.sign(data) == signature
+        }
+    }
+
+    #[test]
+    fn test_signed_roundtrip() {
+        let backend = TronBackend::new(Config::default());
+        let message = TronMessage {
+            header: TronHeader::default(),
+            payload: CompileResult {
+                schema_version: RESULT_SCHEMA_VERSION,
+                ast: vec![1, 2, 3],
+                meta: CompileMeta::default(),
+                source_map: None,
+            },
+        };
+
+        let mut signed = backend.encode_signed(&message, &SumSigner(7)).unwrap();
+        let decoded: TronMessage<CompileResult> =
+            backend.decode_verified(&signed, &SumSigner(7)).unwrap();
+        assert_ne!(decoded.header.flags & flags::SIGNED, 0);
+        assert_eq!(decoded.payload.ast, vec![1, 2, 3]);
+
+        let err = backend
+            .decode_verified::<CompileResult>(&signed, &SumSigner(8))
+            .unwrap_err();
+        assert_eq!(err.code, ErrorCode::SignatureInvalid);
+
+        signed[10] ^= 0xFF;
+        assert!(backend
+            .decode_verified::<CompileResult>(&signed, &SumSigner(7))
+            .is_err());
+    }
+
+    #[test]
+    fn test_attachments_roundtrip() {
+        let backend = TronBackend::new(Config::default());
+        let message = TronMessage {
+            header: TronHeader::default(),
+            payload: CompileResult {
+                schema_version: RESULT_SCHEMA_VERSION,
+                ast: vec![1, 2, 3],
+                meta: CompileMeta::default(),
+                source_map: None,
+            },
+        };
+        let source_map = br#"{"version":3,"mappings":"AAAA"}"#;
+        let snapshot = [0u8, 1, 2, 255];
+
+        let encoded = backend
+            .encode_with_attachments(
+                &message,
+                &[("source_map", source_map), ("dom_snapshot", &snapshot)],
+            )
+            .unwrap();
+        let (decoded, attachments) = backend
+            .decode_with_attachments::<CompileResult>(&encoded)
+            .unwrap();
+
+        assert_ne!(decoded.header.flags & flags::ATTACHMENTS, 0);
+        assert_eq!(decoded.payload.ast, vec![1, 2, 3]);
+        assert_eq!(attachments.len(), 2);
+        assert_eq!(attachments.get("dom_snapshot"), Some(&snapshot[..]));
+        assert_eq!(attachments.get("source_map"), Some(&source_map[..]));
+        assert_eq!(attachments.get("coverage"), None);
+
+        let truncated = &encoded[..encoded.len() - 1];
+        assert!(backend
+            .decode_with_attachments::<CompileResult>(truncated)
+            .is_err());
+    }
+
+    #[cfg(feature = "signing")]
+    #[test]
+    fn test_ed25519_signing() {
+        let key = ed25519_dalek::SigningKey::from_bytes(&[42; 32]);
+        let backend = TronBackend::new(Config::default());
+        let message = TronMessage {
+            header: TronHeader::default(),
+            payload: "precompiled".to_string(),
+        };
+
+        let signed = backend.encode_signed(&message, &key).unwrap();
+        let decoded: TronMessage<String> = backend
+            .decode_verified(&signed, &key.verifying_key())
+            .unwrap();
+        assert_eq!(decoded.payload, "precompiled");
+    }
+
+    #[tokio::test]
+    async fn test_compile() {
+        let backend = TronBackend::new(Config::default());
+        backend.initialize().await.unwrap();
+
+        let result = backend
+            .compile(CompileRequest {
+                source: "toggle .active".to_string(),
+                language: None,
+                options: None,
+            })
+            .await
+            .unwrap();
+
+        assert!(result.meta.compile_time_ms.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_compile_stream_enforces_size_limit() {
+        let backend = TronBackend::new(Config {
+            max_source_bytes: 16,
+            ..Default::default()
+        });
+
+        let result = backend
+            .compile_stream(&b"toggle .active"[..], None, None)
+            .await
+            .unwrap();
+        assert!(result.meta.compile_time_ms.is_some());
+
+        let err = backend
+            .compile_stream(&b"toggle .active on #button"[..], None, None)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidMessage);
+        let context = err.context.unwrap();
+        assert_eq!(context["limit"], "max_source_bytes");
+        assert_eq!(context["max"], 16);
+    }
+
+    #[tokio::test]
+    async fn test_compile_rejects_oversized_source() {
+        let backend = TronBackend::new(Config {
+            max_source_bytes: 8,
+            ..Default::default()
+        });
+
+        let err = backend
+            .compile(CompileRequest {
+                source: "toggle .active".to_string(),
+                language: None,
+                options: None,
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidMessage);
+        assert_eq!(err.context.unwrap()["actual"], 14);
+
+        let err = backend
+            .execute(ExecuteRequest {
+                code: CodeSource::Source("toggle .active".to_string()),
+                context: None,
+                target: None,
+                idempotency_key: None,
+                dry_run: None,
+                extensions: None,
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidMessage);
+        assert_eq!(err.context.unwrap()["limit"], "max_source_bytes");
+    }
+
+    #[test]
+    fn test_stats_keep_recent_errors() {
+        let backend = TronBackend::new(Config::default());
+        for i in 0..RECENT_ERROR_LIMIT + 5 {
+            backend.record_error(&TronError {
+                code: ErrorCode::SyntaxError,
+                message: format!("error {}", i),
+                context: None,
+            });
+        }
+
+        let stats = backend.stats();
+        assert!(!stats.ready);
+        assert_eq!(stats.recent_errors.len(), RECENT_ERROR_LIMIT);
+        assert_eq!(
+            stats.recent_errors[0].message,
+            format!("error {}", RECENT_ERROR_LIMIT + 4)
+        );
+    }
+
+    #[cfg(feature = "axum")]
+    #[tokio::test]
+    async fn test_playground_forces_dry_run() {
+        use axum::{extract::State, Json};
+        use axum_integration::*;
+
+        let backend = Arc::new(TronBackend::new(Config::default()));
+        let request = ExecuteRequest {
+            code: CodeSource::Source("fetch /admin/delete".to_string()),
+            context: None,
+            target: None,
+            idempotency_key: Some("k".to_string()),
+            dry_run: Some(false),
+            extensions: None,
+        };
+
+        let Json(result) = playground_execute_handler(State(backend.clone()), Json(request))
+            .await
+            .ok()
+            .unwrap();
+        assert!(result.plan.is_some());
+        assert_eq!(backend.stats().idempotency_entries, 0);
+
+        let oversized = ExecuteRequest {
+            code: CodeSource::Source("x".repeat(PLAYGROUND_MAX_SOURCE_BYTES + 1)),
+            context: None,
+            target: None,
+            idempotency_key: None,
+            dry_run: None,
+            extensions: None,
+        };
+        assert!(
+            playground_execute_handler(State(backend.clone()), Json(oversized))
+                .await
+                .is_err()
+        );
+        assert_eq!(backend.stats().recent_errors.len(), 1);
+    }
+
+    #[test]
+    fn test_compile_result_fingerprint() {
+        let result = |ast: Vec<u8>, compile_time_ms| CompileResult {
+            schema_version: RESULT_SCHEMA_VERSION,
+            ast,
+            meta: CompileMeta {
+                compile_time_ms: Some(compile_time_ms),
+                ..Default::default()
+            },
+            source_map: None,
+        };
+
+        assert_eq!(
+            result(vec![1, 2], 1.0).fingerprint(),
+            result(vec![1, 2], 9.0).fingerprint()
+        );
+        assert_ne!(
+            result(vec![1, 2], 1.0).fingerprint(),
+            result(vec![2, 1], 1.0).fingerprint()
+        );
+
+        let mut warned = result(vec![1, 2], 1.0);
+        warned.meta.warnings = Some(vec!["deprecated syntax".to_string()]);
+        assert_ne!(warned.fingerprint(), result(vec![1, 2], 1.0).fingerprint());
+    }
 
-        let version = u16::from_be_bytes([data[4], data[5]]);
-        let flags = u16::from_be_bytes([data[6], data[7]]);
+    #[test]
+    fn test_result_schema_tolerant_decoding() {
+        // Written before schema_version existed
+        let legacy: ExecuteResult = serde_json::from_str(r#"{"success":true,"meta":{}}"#).unwrap();
+        assert_eq!(legacy.schema_version, 1);
+
+        // Written by a newer release with fields this build doesn't know
+        let newer: CompileResult = serde_json::from_str(
+            r#"{"schema_version":2,"ast":[],"meta":{"cache_hit":true},"bytecode":[1]}"#,
+        )
+        .unwrap();
+        assert_eq!(newer.schema_version, 2);
+        assert!(newer.ast.is_empty());
+    }
 
-        // Parse JSON payload
-        let payload: T = serde_json::from_slice(&data[8..]).map_err(|e| TronError {
-            code: ErrorCode::InvalidMessage,
-            message: format!("Failed to decode payload: {}", e),
-            context: None,
-        })?;
+    #[tokio::test]
+    async fn test_verify_provenance() {
+        let backend = TronBackend::new(Config::default());
+        let mut artifact = backend
+            .compile(CompileRequest {
+                source: "toggle .active".to_string(),
+                language: None,
+                options: None,
+            })
+            .await
+            .unwrap();
+        assert!(verify_provenance(&artifact).is_ok());
 
-        Ok(TronMessage {
-            header: TronHeader {
-                magic,
-                version,
-                flags,
-            },
-            payload,
-        })
+        let provenance = artifact.meta.provenance.as_mut().unwrap();
+        assert_eq!(provenance.compiler_version, env!("CARGO_PKG_VERSION"));
+        provenance.grammar_version = GRAMMAR_VERSION + 1;
+        assert!(verify_provenance(&artifact).is_err());
+
+        artifact.meta.provenance = None;
+        let err = verify_provenance(&artifact).unwrap_err();
+        assert_eq!(err.code, ErrorCode::UnsupportedVersion);
     }
-}
 
-// =============================================================================
-// Axum Integration
-// =============================================================================
+    #[cfg(feature = "axum")]
+    #[tokio::test]
+    async fn test_compile_handler_etag() {
+        use axum::{
+            extract::State,
+            http::{header, HeaderMap, StatusCode},
+            Json,
+        };
+        use axum_integration::*;
 
-#[cfg(feature = "axum")]
-pub mod axum_integration {
-    use super::*;
-    use axum::{
-        extract::State,
-        http::{header, HeaderMap, StatusCode},
-        response::{IntoResponse, Response},
-        Json,
-    };
+        let backend = Arc::new(TronBackend::new(Config::default()));
+        let request = || CompileRequest {
+            source: "toggle .active".to_string(),
+            language: None,
+            options: None,
+        };
 
-    /// Axum state wrapper
-    pub type TronState = Arc<TronBackend>;
+        let response = compile_handler(State(backend.clone()), HeaderMap::new(), Json(request()))
+            .await
+            .ok()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers()[header::ETAG].clone();
 
-    /// Compile handler for Axum
-    pub async fn compile_handler(
-        State(backend): State<TronState>,
-        Json(request): Json<CompileRequest>,
-    ) -> Result<Json<CompileResult>, TronErrorResponse> {
-        backend
-            .compile(request)
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, etag.clone());
+        let response = compile_handler(State(backend.clone()), headers, Json(request()))
             .await
-            .map(Json)
-            .map_err(TronErrorResponse)
-    }
+            .ok()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
 
-    /// Execute handler for Axum
-    pub async fn execute_handler(
-        State(backend): State<TronState>,
-        Json(request): Json<ExecuteRequest>,
-    ) -> Result<Json<ExecuteResult>, TronErrorResponse> {
-        backend
-            .execute(request)
+        // Strong form of the same tag still matches under weak comparison
+        let strong = etag.to_str().unwrap().trim_start_matches("W/").to_string();
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, strong.parse().unwrap());
+        let response = compile_handler(State(backend.clone()), headers, Json(request()))
+            .await
+            .ok()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        // Same (placeholder) AST, different options: different provenance
+        let mut with_options = request();
+        with_options.options = Some(CompileOptions {
+            semantic: Some(true),
+            ..Default::default()
+        });
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, etag.clone());
+        let response = compile_handler(State(backend), headers, Json(with_options))
             .await
-            .map(Json)
-            .map_err(TronErrorResponse)
+            .ok()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(response.headers()[header::ETAG], etag);
     }
 
-    /// Error response wrapper
-    pub struct TronErrorResponse(pub TronError);
+    #[tokio::test]
+    async fn test_panics_become_crash_reports() {
+        let backend = TronBackend::new(Config::default());
 
-    impl IntoResponse for TronErrorResponse {
-        fn into_response(self) -> Response {
-            let status = match self.0.code {
-                ErrorCode::InvalidMessage | ErrorCode::InvalidPayloadType => StatusCode::BAD_REQUEST,
-                ErrorCode::UnsupportedVersion | ErrorCode::UnsupportedLanguage => {
-                    StatusCode::NOT_IMPLEMENTED
+        let err = backend
+            .catch_panics("execute", "abc123".to_string(), async {
+                if true {
+                    panic!("stack overflow in handler");
                 }
-                ErrorCode::ParseError | ErrorCode::SyntaxError => StatusCode::UNPROCESSABLE_ENTITY,
-                ErrorCode::RuntimeError => StatusCode::INTERNAL_SERVER_ERROR,
-                ErrorCode::Timeout => StatusCode::REQUEST_TIMEOUT,
-                ErrorCode::ServiceUnavailable => StatusCode::SERVICE_UNAVAILABLE,
-                _ => StatusCode::INTERNAL_SERVER_ERROR,
-            };
-
-            (status, Json(self.0)).into_response()
-        }
+                Ok(())
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, ErrorCode::InternalError);
+
+        let id = err.context.unwrap()["crash_report"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        let report = backend.crash_report(&id).unwrap();
+        assert_eq!(report.stage, "execute");
+        assert_eq!(report.script_fingerprint, "abc123");
+        assert_eq!(report.message, "stack overflow in handler");
     }
-}
 
-// =============================================================================
-// Tests
-// =============================================================================
+    #[test]
+    fn test_circuit_breaker_transitions() {
+        let breaker = CircuitBreaker::new(2, Duration::from_millis(20));
+        assert_eq!(breaker.state(), BreakerState::Closed);
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        breaker.record_failure();
+        assert!(breaker.allow());
+        breaker.record_failure();
+        assert_eq!(breaker.state(), BreakerState::Open);
+        assert!(!breaker.allow());
 
-    #[test]
-    fn test_tron_header_default() {
-        let header = TronHeader::default();
-        assert_eq!(header.magic, TRON_MAGIC);
-        assert_eq!(header.version, 1);
-        assert_eq!(header.flags, 0);
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+
+        // Only one trial call at a time while half-open
+        assert!(breaker.allow());
+        assert!(!breaker.allow());
+
+        // A failed trial re-opens immediately
+        breaker.record_failure();
+        assert_eq!(breaker.state(), BreakerState::Open);
+        assert!(!breaker.allow());
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(breaker.allow());
+
+        breaker.record_success();
+        assert_eq!(breaker.state(), BreakerState::Closed);
+
+        let backend = TronBackend::new(Config::default());
+        assert_eq!(backend.stats().native_breaker, BreakerState::Closed);
     }
 
+    #[cfg(feature = "native")]
     #[test]
-    fn test_encode_decode_roundtrip() {
+    fn test_native_roundtrip() {
         let backend = TronBackend::new(Config::default());
-
         let message = TronMessage {
-            header: TronHeader::default(),
+            header: TronHeader {
+                flags: flags::CHECKSUM,
+                ..Default::default()
+            },
             payload: CompileRequest {
                 source: "toggle .active".to_string(),
                 language: Some("en".to_string()),
@@ -576,19 +3096,59 @@ mod tests {
             },
         };
 
+        // Native bytes dispatch to `decode_native`
         let encoded = backend.encode(&message).unwrap();
+        assert!(!encoded.starts_with(&TRON_MAGIC.to_be_bytes()));
         let decoded: TronMessage<CompileRequest> = backend.decode(&encoded).unwrap();
+        assert_eq!(decoded.header.flags, flags::CHECKSUM);
+        assert_eq!(decoded.payload.source, "toggle .active");
+        assert_eq!(decoded.payload.language.as_deref(), Some("en"));
 
-        assert_eq!(decoded.header.magic, TRON_MAGIC);
+        // Magic-prefixed bytes still take the emulated path
+        let emulated = backend.encode_emulated(&message).unwrap();
+        let decoded: TronMessage<CompileRequest> = backend.decode(&emulated).unwrap();
         assert_eq!(decoded.payload.source, "toggle .active");
+
+        assert!(backend.decode::<CompileRequest>(b"not lite3").is_err());
+    }
+
+    #[cfg(feature = "native")]
+    #[test]
+    fn test_payload_errors_do_not_trip_breaker() {
+        let backend = TronBackend::new(Config {
+            breaker_threshold: 1,
+            ..Default::default()
+        });
+
+        // Non-string map keys can't be serialized to JSON
+        let message = TronMessage {
+            header: TronHeader::default(),
+            payload: HashMap::from([((1, 2), "value")]),
+        };
+        assert!(backend.encode(&message).is_err());
+        assert_eq!(backend.stats().native_breaker, BreakerState::Closed);
     }
 
     #[tokio::test]
-    async fn test_compile() {
-        let backend = TronBackend::new(Config::default());
-        backend.initialize().await.unwrap();
+    async fn test_stage_observer() {
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<(Stage, bool)>>);
+        impl StageObserver for Recorder {
+            fn on_stage(&self, stage: Stage, _elapsed: Duration, success: bool) {
+                self.0.lock().unwrap().push((stage, success));
+            }
+        }
 
-        let result = backend
+        let recorder = Arc::new(Recorder::default());
+        let backend = TronBackend::new(Config::default()).with_observer(recorder.clone());
+
+        let message = TronMessage {
+            header: TronHeader::default(),
+            payload: "toggle .active".to_string(),
+        };
+        let data = backend.encode(&message).unwrap();
+        let _: TronMessage<String> = backend.decode(&data).unwrap();
+        backend
             .compile(CompileRequest {
                 source: "toggle .active".to_string(),
                 language: None,
@@ -596,7 +3156,411 @@ mod tests {
             })
             .await
             .unwrap();
+        assert!(backend.decode::<String>(b"junk").is_err());
+
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            vec![
+                (Stage::Encode, true),
+                (Stage::Decode, true),
+                (Stage::Compile, true),
+                (Stage::Decode, false),
+            ]
+        );
+    }
 
-        assert!(result.meta.compile_time_ms.is_some());
+    #[tokio::test]
+    async fn test_self_test_passes() {
+        let backend = TronBackend::new(Config {
+            compression: true,
+            ..Default::default()
+        });
+        let report = backend.self_test().await;
+
+        assert!(report.passed(), "{:?}", report);
+        let status = |name: &str| {
+            report
+                .checks
+                .iter()
+                .find(|check| check.name == name)
+                .map(|check| check.status)
+        };
+        assert_eq!(status("encode_decode"), Some(CheckStatus::Pass));
+        assert_eq!(status("execute"), Some(CheckStatus::Pass));
+        assert_eq!(status("compression"), Some(CheckStatus::Skipped));
+        assert_eq!(status("checksums"), None);
+    }
+
+    #[test]
+    fn test_error_localization() {
+        let request = CompileRequest {
+            source: "alternar .activo".to_string(),
+            language: Some("es".to_string()),
+            options: None,
+        };
+        assert_eq!(request.locale(), Some("es"));
+
+        let error = TronError {
+            code: ErrorCode::SyntaxError,
+            message: "unexpected token".to_string(),
+            context: None,
+        }
+        .localize(request.locale());
+        assert_eq!(error.message, "Error de sintaxis: unexpected token");
+
+        assert_eq!(
+            ErrorCode::Timeout.summary(Some("fr")),
+            "Execution timed out"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_compile_localizes_errors() {
+        let backend = TronBackend::new(Config {
+            max_source_bytes: 4,
+            ..Default::default()
+        });
+
+        let err = backend
+            .compile(CompileRequest {
+                source: "alternar .activo".to_string(),
+                language: Some("es".to_string()),
+                options: None,
+            })
+            .await
+            .unwrap_err();
+        assert!(err.message.starts_with("Mensaje no válido: "));
+    }
+
+    #[test]
+    fn test_dialect_wire_format() {
+        let options = CompileOptions {
+            dialect: Some(Dialect::Strict),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&options).unwrap();
+        assert_eq!(json["dialect"], "strict");
+
+        let decoded: CompileOptions = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.dialect, Some(Dialect::Strict));
+    }
+
+    #[tokio::test]
+    async fn test_compile_warns_dialect_not_enforced() {
+        let backend = TronBackend::new(Config::default());
+        let request = |dialect| CompileRequest {
+            source: "toggle .active".to_string(),
+            language: None,
+            options: Some(CompileOptions {
+                dialect,
+                ..Default::default()
+            }),
+        };
+
+        let result = backend.compile(request(None)).await.unwrap();
+        assert_eq!(result.meta.warnings, None);
+
+        let result = backend
+            .compile(request(Some(Dialect::Strict)))
+            .await
+            .unwrap();
+        let warnings = result.meta.warnings.unwrap();
+        assert!(warnings[0].contains("not enforced"));
+    }
+
+    #[tokio::test]
+    async fn test_compile_grammar_version() {
+        let backend = TronBackend::new(Config::default());
+
+        let request = |version| CompileRequest {
+            source: "toggle .active".to_string(),
+            language: None,
+            options: Some(CompileOptions {
+                grammar_version: Some(version),
+                ..Default::default()
+            }),
+        };
+
+        let result = backend.compile(request(GRAMMAR_VERSION)).await.unwrap();
+        assert_eq!(result.meta.grammar_version, Some(GRAMMAR_VERSION));
+
+        let err = backend
+            .compile(request(GRAMMAR_VERSION + 1))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, ErrorCode::UnsupportedVersion);
+    }
+
+    #[test]
+    fn test_context_fork_copy_on_write() {
+        let mut parent = ExecutionContext::default();
+        parent.globals_mut()["config"] = serde_json::json!({ "theme": "dark" });
+
+        let mut child = parent.fork();
+        assert!(Arc::ptr_eq(
+            parent.globals.as_ref().unwrap(),
+            child.globals.as_ref().unwrap()
+        ));
+
+        child.globals_mut()["config"]["theme"] = serde_json::json!("light");
+        assert_eq!(parent.globals.as_ref().unwrap()["config"]["theme"], "dark");
+        assert_eq!(child.globals.as_ref().unwrap()["config"]["theme"], "light");
+    }
+
+    #[tokio::test]
+    async fn test_execute_idempotency_key() {
+        let backend = TronBackend::new(Config::default());
+
+        let request = ExecuteRequest {
+            code: CodeSource::Source("send refresh to #cart".to_string()),
+            context: None,
+            target: None,
+            idempotency_key: Some("order-42".to_string()),
+            dry_run: None,
+            extensions: None,
+        };
+
+        let first = backend.execute(request.clone()).await.unwrap();
+        assert_eq!(first.meta.replayed, None);
+
+        let retry = backend.execute(request).await.unwrap();
+        assert_eq!(retry.meta.replayed, Some(true));
+        assert_eq!(retry.meta.execution_time_ms, first.meta.execution_time_ms);
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_key_reused_for_different_request() {
+        let backend = TronBackend::new(Config::default());
+
+        let mut request = ExecuteRequest {
+            code: CodeSource::Source("send refresh to #cart".to_string()),
+            context: None,
+            target: None,
+            idempotency_key: Some("order-42".to_string()),
+            dry_run: None,
+            extensions: None,
+        };
+        backend.execute(request.clone()).await.unwrap();
+
+        request.code = CodeSource::Source("fetch /x".to_string());
+        let err = backend.execute(request.clone()).await.unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidMessage);
+
+        request.code = CodeSource::Source("send refresh to #cart".to_string());
+        let mut context = ExecutionContext::default();
+        context.set_local("count", &1).unwrap();
+        request.context = Some(context);
+        let err = backend.execute(request).await.unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidMessage);
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_window_expiry_and_cap() {
+        let request = |key: &str| ExecuteRequest {
+            code: CodeSource::Source("send refresh to #cart".to_string()),
+            context: None,
+            target: None,
+            idempotency_key: Some(key.to_string()),
+            dry_run: None,
+            extensions: None,
+        };
+
+        let backend = TronBackend::new(Config {
+            idempotency_window: Duration::ZERO,
+            ..Default::default()
+        });
+        backend.execute(request("order-42")).await.unwrap();
+        let retry = backend.execute(request("order-42")).await.unwrap();
+        assert_eq!(retry.meta.replayed, None);
+
+        let backend = TronBackend::new(Config {
+            max_idempotency_entries: 2,
+            ..Default::default()
+        });
+        for key in ["a", "b", "c"] {
+            backend.execute(request(key)).await.unwrap();
+        }
+        assert_eq!(backend.stats().idempotency_entries, 2);
+        let retry = backend.execute(request("a")).await.unwrap();
+        assert_eq!(retry.meta.replayed, None);
+        let retry = backend.execute(request("c")).await.unwrap();
+        assert_eq!(retry.meta.replayed, Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_key_waits_for_in_flight_execution() {
+        let backend = Arc::new(TronBackend::new(Config::default()));
+        let request = ExecuteRequest {
+            code: CodeSource::Source("send refresh to #cart".to_string()),
+            context: None,
+            target: None,
+            idempotency_key: Some("order-42".to_string()),
+            dry_run: None,
+            extensions: None,
+        };
+
+        // Hold the key as an in-flight execution would
+        let guard = match backend
+            .claim_idempotency_key("order-42", &request)
+            .await
+            .unwrap()
+        {
+            Claim::Execute(guard) => guard,
+            Claim::Replay(_) => panic!("key should be unclaimed"),
+        };
+
+        let retry = tokio::spawn({
+            let backend = backend.clone();
+            let request = request.clone();
+            async move { backend.execute(request).await }
+        });
+        tokio::task::yield_now().await;
+        assert!(!retry.is_finished());
+
+        let result = backend.execute_uncached(request).await.unwrap();
+        guard.complete(&result);
+
+        let retry = retry.await.unwrap().unwrap();
+        assert_eq!(retry.meta.replayed, Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_execute_dry_run_returns_plan() {
+        let backend = TronBackend::new(Config::default());
+
+        let mut request = ExecuteRequest {
+            code: CodeSource::Source("fetch /api/items then put it into #list".to_string()),
+            context: None,
+            target: None,
+            idempotency_key: None,
+            dry_run: Some(true),
+            extensions: None,
+        };
+        let result = backend.execute(request.clone()).await.unwrap();
+        assert!(result.plan.is_some());
+
+        request.dry_run = None;
+        let result = backend.execute(request).await.unwrap();
+        assert!(result.plan.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_does_not_claim_idempotency_key() {
+        let backend = TronBackend::new(Config::default());
+
+        let mut request = ExecuteRequest {
+            code: CodeSource::Source("fetch /api/items then put it into #list".to_string()),
+            context: None,
+            target: None,
+            idempotency_key: Some("order-42".to_string()),
+            dry_run: Some(true),
+            extensions: None,
+        };
+        backend.execute(request.clone()).await.unwrap();
+        assert_eq!(backend.stats().idempotency_entries, 0);
+
+        request.dry_run = None;
+        let result = backend.execute(request.clone()).await.unwrap();
+        assert_eq!(result.meta.replayed, None);
+        assert!(result.plan.is_none());
+
+        // A dry run after the real execution doesn't replay it either
+        request.dry_run = Some(true);
+        let result = backend.execute(request).await.unwrap();
+        assert_eq!(result.meta.replayed, None);
+        assert!(result.plan.is_some());
+    }
+
+    #[test]
+    fn test_effect_plan_filter_and_apply() {
+        struct Recorder(Vec<Effect>);
+
+        impl EffectTarget for Recorder {
+            fn apply_effect(&mut self, effect: &Effect) -> TronResult<()> {
+                self.0.push(effect.clone());
+                Ok(())
+            }
+        }
+
+        let plan = EffectPlan {
+            effects: vec![
+                Effect::Fetch {
+                    url: "/api/items".to_string(),
+                    method: "GET".to_string(),
+                    body: None,
+                },
+                Effect::DomWrite {
+                    command: "put".to_string(),
+                    target: "#list".to_string(),
+                    value: Some(serde_json::json!("<li>item</li>")),
+                },
+            ],
+        };
+
+        let json = serde_json::to_value(&plan).unwrap();
+        assert_eq!(json["effects"][1]["type"], "dom_write");
+
+        let no_network = plan.filter(|e| !matches!(e, Effect::Fetch { .. }));
+        let mut recorder = Recorder(Vec::new());
+        apply(&no_network, &mut recorder).unwrap();
+        assert_eq!(recorder.0.len(), 1);
+        assert!(matches!(recorder.0[0], Effect::DomWrite { .. }));
+    }
+
+    #[test]
+    fn test_host_value_interop() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Cart {
+            items: u32,
+            total: f64,
+        }
+
+        let mut context = ExecutionContext::default();
+        context
+            .set_local(
+                "cart",
+                &Cart {
+                    items: 2,
+                    total: 9.5,
+                },
+            )
+            .unwrap();
+        context.set_global("db", &Handle { id: 7 }).unwrap();
+
+        assert_eq!(
+            context.local::<Cart>("cart").unwrap(),
+            Some(Cart {
+                items: 2,
+                total: 9.5
+            })
+        );
+        assert_eq!(
+            context.global::<Handle>("db").unwrap(),
+            Some(Handle { id: 7 })
+        );
+        assert_eq!(context.globals.as_ref().unwrap()["db"]["$handle"], 7);
+        assert!(context.local::<Cart>("missing").unwrap().is_none());
+        assert!(context.local::<Handle>("cart").is_err());
+    }
+
+    #[test]
+    fn test_resource_table_leak_report() {
+        let mut table = ResourceTable::new();
+        let conn = table.insert("db connection", String::from("postgres://"));
+        let file = table.insert("upload", vec![0u8; 4]);
+
+        assert_eq!(table.get::<String>(conn).unwrap(), "postgres://");
+        assert!(table.get::<String>(file).is_none());
+        assert!(table.remove::<String>(file).is_none());
+        assert_eq!(table.remove::<Vec<u8>>(file), Some(vec![0u8; 4]));
+
+        let leaked = table.close();
+        assert_eq!(
+            leaked,
+            vec![LeakedResource {
+                handle: conn,
+                label: "db connection".to_string(),
+            }]
+        );
     }
 }
@@ -31,6 +31,7 @@ export type {
   // Requests
   CompileRequest,
   CompileOptions,
+  Extensions,
   ExecuteRequest,
   ExecutionContext,
   ValidateRequest,
@@ -42,8 +43,11 @@ export type {
   // Results
   CompileResult,
   CompileMeta,
+  Provenance,
   ExecuteResult,
   ExecuteMeta,
+  EffectPlan,
+  Effect,
   ValidateResult,
   TranslateResult,
   HydrateResult,
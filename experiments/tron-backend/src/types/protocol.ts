@@ -30,6 +30,8 @@ export enum TronFlags {
   STREAMING = 1 << 1,
   ENCRYPTED = 1 << 2,
   CHECKSUM = 1 << 3,
+  SIGNED = 1 << 4,
+  ATTACHMENTS = 1 << 5,
 }
 
 /**
@@ -109,6 +111,24 @@ export interface CompileOptions {
   sourceMap?: boolean;
   /** Target environment */
   target?: 'browser' | 'node' | 'universal';
+  /** Locale for error messages (defaults to the request language) */
+  locale?: string;
+  /** Handling of legacy _hyperscript constructs (not enforced yet) */
+  dialect?: 'strict' | 'compatible' | 'permissive';
+  /** Grammar version to compile against (defaults to the latest) */
+  grammarVersion?: number;
+  /** Options for third-party plugins */
+  extensions?: Extensions;
+}
+
+/**
+ * Plugin options carried through the protocol untouched
+ */
+export interface Extensions {
+  /** Version of the extensions bag format */
+  version: number;
+  /** Plugin-defined keys (namespacing such as "my-plugin.option" is recommended) */
+  values: Record<string, unknown>;
 }
 
 export interface CompileMeta {
@@ -122,9 +142,25 @@ export interface CompileMeta {
   warnings?: string[];
   /** Compilation time in ms */
   compileTimeMs?: number;
+  /** Grammar version the source was compiled against */
+  grammarVersion?: number;
+  /** Build information of the compiler that produced the artifact */
+  provenance?: Provenance;
+}
+
+export interface Provenance {
+  /** Version of the compiler that produced the artifact */
+  compilerVersion: string;
+  grammarVersion: number;
+  /** Features enabled in the producing build */
+  features: string[];
+  /** Fingerprint of the compile options used */
+  optionsHash: string;
 }
 
 export interface CompileResult {
+  /** Result schema version (absent from results that predate it) */
+  schemaVersion?: number;
   /** Compiled AST (TRON-encoded) */
   ast: Uint8Array;
   /** Compilation metadata */
@@ -151,6 +187,15 @@ export interface ExecuteRequest {
   context?: ExecutionContext;
   /** Target element selector (for browser simulation) */
   target?: string;
+  /**
+   * Retries with the same key return the stored result instead of executing
+   * again; reusing a key for a different request is an error
+   */
+  idempotencyKey?: string;
+  /** Record side effects into `ExecuteResult.plan` instead of performing them */
+  dryRun?: boolean;
+  /** Options for third-party plugins */
+  extensions?: Extensions;
 }
 
 export interface ExecutionContext {
@@ -191,10 +236,16 @@ export interface ExecuteMeta {
   executionTimeMs?: number;
   /** Commands executed */
   commandsExecuted?: number;
-  /** Side effects produced */
+  /** Set when the result was replayed for a repeated idempotency key */
+  replayed?: boolean;
+  /**
+   * Side effects produced
+   * @deprecated Use `ExecuteResult.plan`, recorded for dry runs
+   */
   sideEffects?: SideEffect[];
 }
 
+/** @deprecated Use `Effect` */
 export interface SideEffect {
   type: 'dom' | 'network' | 'storage' | 'console';
   action: string;
@@ -203,16 +254,40 @@ export interface SideEffect {
 }
 
 export interface ExecuteResult {
+  /** Result schema version (absent from results that predate it) */
+  schemaVersion?: number;
   /** Execution success */
   success: boolean;
   /** Return value (if any) */
   value?: unknown;
   /** Updated context */
   context?: ExecutionContext;
+  /** Effects that would have been performed (dry runs only) */
+  plan?: EffectPlan;
   /** Execution metadata */
   meta: ExecuteMeta;
 }
 
+/**
+ * Ordered side effects recorded during a dry run
+ */
+export interface EffectPlan {
+  effects: Effect[];
+}
+
+/**
+ * A single side effect with its arguments, discriminated by `type`
+ */
+export type Effect =
+  /** Outbound HTTP request (`fetch`) */
+  | { type: 'fetch'; url: string; method: string; body?: unknown }
+  /** DOM mutation (`put`, `add`, `remove`, `toggle`, `set`) */
+  | { type: 'dom_write'; command: string; target: string; value?: unknown }
+  /** Event dispatch (`send`, `trigger`) */
+  | { type: 'send'; event: string; target: string; detail?: unknown }
+  /** Any other side-effecting command */
+  | { type: 'command'; command: string; args?: unknown };
+
 // =============================================================================
 // Events
 // =============================================================================
@@ -453,6 +528,7 @@ export enum TronErrorCode {
   UNSUPPORTED_VERSION = 1001,
   INVALID_PAYLOAD_TYPE = 1002,
   CHECKSUM_MISMATCH = 1003,
+  SIGNATURE_INVALID = 1004,
 
   // Compilation errors (2xxx)
   PARSE_ERROR = 2000,